@@ -0,0 +1,43 @@
+extern crate botnanars;
+use botnanars::Botnana;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread, time,
+};
+
+fn main() {
+    println!("Create botnana.");
+    let mut botnana = Botnana::new();
+    let is_opened = Arc::new(AtomicBool::new(false));
+
+    let opened = is_opened.clone();
+    botnana.on_open(move |msg| {
+        opened.store(true, Ordering::SeqCst);
+        println!("WS Open : {}", msg);
+    });
+    let opened = is_opened.clone();
+    botnana.on_error(move |msg| {
+        opened.store(false, Ordering::SeqCst);
+        println!("WS Error : {}", msg);
+    });
+    botnana.on_tagname("real_position", 0, |position, channel, value| {
+        println!("real_position ({}, {}) : {}", position, channel, value);
+    });
+
+    println!("Connect to botnana.");
+    botnana.connect();
+
+    println!("Wait for connection.");
+    while !is_opened.load(Ordering::SeqCst) {
+        thread::sleep(time::Duration::from_millis(1000));
+    }
+
+    println!("Enable auto query.");
+    botnana.enable_auto_qurey();
+
+    println!("Wait for 2 seconds.");
+    thread::sleep(time::Duration::from_millis(2000));
+}
@@ -0,0 +1,110 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// EtherCAT 從站位址
+/// alias, EtherCAT Slave 的站號別名。
+/// position, EtherCAT Slave 的站號，最靠近主站的為 1, 依序遞增排列。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SlaveAddr {
+    /// 以 alias 選定從站
+    Alias(u16),
+    /// 以 position 選定從站
+    Position(u16),
+}
+
+impl SlaveAddr {
+    /// 依舊的使用規則建立，當 alias > 0，就以 alias 選定從站，
+    /// 當 alias = 0, 就以 position 選定從站。
+    pub fn new(alias: u16, position: u16) -> SlaveAddr {
+        if alias > 0 {
+            SlaveAddr::Alias(alias)
+        } else {
+            SlaveAddr::Position(position)
+        }
+    }
+
+    /// JSON-RPC 參數中的 alias 與 position，沒有使用的欄位為 0
+    pub fn alias_and_position(&self) -> (u16, u16) {
+        match *self {
+            SlaveAddr::Alias(alias) => (alias, 0),
+            SlaveAddr::Position(position) => (0, position),
+        }
+    }
+}
+
+/// 在 Forth 命令中取得 slave position 的寫法
+impl fmt::Display for SlaveAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SlaveAddr::Alias(alias) => write!(f, "{} ec-a>n", alias),
+            SlaveAddr::Position(position) => write!(f, "{}", position),
+        }
+    }
+}
+
+/// 驅動器位址 (從站與 channel)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct DriveAddr {
+    /// 從站
+    pub slave: SlaveAddr,
+    /// Channel, 從 1 開始
+    pub channel: u16,
+}
+
+impl DriveAddr {
+    /// 依舊的使用規則建立，當 alias > 0，就以 alias 選定從站，
+    /// 當 alias = 0, 就以 position 選定從站。
+    pub fn new(alias: u16, position: u16, channel: u16) -> DriveAddr {
+        DriveAddr {
+            slave: SlaveAddr::new(alias, position),
+            channel,
+        }
+    }
+
+    /// 以 alias 選定從站
+    pub fn alias(alias: u16, channel: u16) -> DriveAddr {
+        DriveAddr {
+            slave: SlaveAddr::Alias(alias),
+            channel,
+        }
+    }
+
+    /// 以 position 選定從站
+    pub fn position(position: u16, channel: u16) -> DriveAddr {
+        DriveAddr {
+            slave: SlaveAddr::Position(position),
+            channel,
+        }
+    }
+}
+
+/// API 選定驅動器的方式，可以是驅動器位址或是 axis 名稱
+/// axis 名稱由 data pool 中的 axis 設定轉換成驅動器位址 (參考 `load_axis_config`)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DriveTarget {
+    /// 驅動器位址
+    Addr(DriveAddr),
+    /// Axis 名稱
+    Axis(String),
+}
+
+impl From<DriveAddr> for DriveTarget {
+    fn from(addr: DriveAddr) -> DriveTarget {
+        DriveTarget::Addr(addr)
+    }
+}
+
+impl<'a> From<&'a str> for DriveTarget {
+    fn from(name: &'a str) -> DriveTarget {
+        DriveTarget::Axis(name.to_owned())
+    }
+}
+
+impl From<String> for DriveTarget {
+    fn from(name: String) -> DriveTarget {
+        DriveTarget::Axis(name)
+    }
+}
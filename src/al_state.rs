@@ -0,0 +1,88 @@
+use error::{Error, Result};
+use std::convert::TryFrom;
+
+/// AL status bit 4: error indication (從站無法進入要求的狀態)
+pub const AL_STATUS_ERROR: u8 = 0x10;
+
+/// EtherCAT AL state (AL status 0x0130 的 bit 0 ~ 3)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AlState {
+    /// INIT
+    Init,
+    /// PRE-OP
+    PreOp,
+    /// BOOT
+    Bootstrap,
+    /// SAFE-OP
+    SafeOp,
+    /// OP
+    Op,
+}
+
+impl AlState {
+    /// 由 AL status 解碼，忽略 error indication bit，不在狀態表內時為 None
+    pub fn from_al_status(status: u8) -> Option<AlState> {
+        AlState::try_from(status & 0x0F).ok()
+    }
+}
+
+impl From<AlState> for u8 {
+    fn from(state: AlState) -> u8 {
+        match state {
+            AlState::Init => 1,
+            AlState::PreOp => 2,
+            AlState::Bootstrap => 3,
+            AlState::SafeOp => 4,
+            AlState::Op => 8,
+        }
+    }
+}
+
+impl TryFrom<u8> for AlState {
+    type Error = Error;
+
+    fn try_from(state: u8) -> Result<AlState> {
+        match state {
+            1 => Ok(AlState::Init),
+            2 => Ok(AlState::PreOp),
+            3 => Ok(AlState::Bootstrap),
+            4 => Ok(AlState::SafeOp),
+            8 => Ok(AlState::Op),
+            _ => Err(Error::Parse(format!("Unknown AL state: {}", state))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_al_status() {
+        assert_eq!(AlState::from_al_status(0x01), Some(AlState::Init));
+        assert_eq!(AlState::from_al_status(0x02), Some(AlState::PreOp));
+        assert_eq!(AlState::from_al_status(0x03), Some(AlState::Bootstrap));
+        assert_eq!(AlState::from_al_status(0x04), Some(AlState::SafeOp));
+        assert_eq!(AlState::from_al_status(0x08), Some(AlState::Op));
+        // error indication bit 不影響狀態
+        assert_eq!(AlState::from_al_status(0x14), Some(AlState::SafeOp));
+        assert_eq!(AlState::from_al_status(0x00), None);
+        assert_eq!(AlState::from_al_status(0x06), None);
+    }
+
+    #[test]
+    fn state_round_trip() {
+        for state in [
+            AlState::Init,
+            AlState::PreOp,
+            AlState::Bootstrap,
+            AlState::SafeOp,
+            AlState::Op,
+        ]
+        .iter()
+        {
+            assert_eq!(AlState::try_from(u8::from(*state)), Ok(*state));
+        }
+        assert!(AlState::try_from(0x18).is_err());
+    }
+}
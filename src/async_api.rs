@@ -0,0 +1,174 @@
+use address::DriveTarget;
+use botnana::Botnana;
+use error::{Error, Result};
+use json_api::Reply;
+use serde_json::Value;
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+/// 以 Future 等待 JSON-RPC 的回應
+impl Future for Reply {
+    type Output = Result<Value>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<Value>> {
+        if let Some(x) = self.try_get() {
+            return Poll::Ready(x);
+        }
+        // 先登記 waker 再檢查一次，避免在兩者之間收到回應而沒有被喚醒
+        self.set_waker(cx.waker());
+        match self.try_get() {
+            Some(x) => Poll::Ready(x),
+            None => Poll::Pending,
+        }
+    }
+}
+
+/// Async Botnana
+/// 以 Future 包裝 Botnana 的 API，可以直接在 executor 中 `.await`，不需要另外建立等待用的 thread
+#[derive(Clone)]
+pub struct AsyncBotnana {
+    botnana: Botnana,
+}
+
+impl AsyncBotnana {
+    /// New
+    pub fn new(botnana: Botnana) -> AsyncBotnana {
+        AsyncBotnana { botnana }
+    }
+
+    /// 取得原本的 Botnana，用來呼叫其他 API
+    pub fn botnana(&mut self) -> &mut Botnana {
+        &mut self.botnana
+    }
+
+    /// Connect to botnana
+    /// 連線成功時完成，連線失敗時回傳錯誤
+    pub fn connect(&mut self) -> Connect {
+        Connect {
+            botnana: self.botnana.clone(),
+            started: false,
+            waker: None,
+        }
+    }
+
+    /// Evaluate
+    /// script 執行完畢時完成，abort 時回傳 `Error::ForthAbort`
+    pub fn evaluate(&mut self, script: &str) -> Evaluate {
+        Evaluate {
+            reply: self.botnana.try_evaluate(script),
+        }
+    }
+
+    /// version.get
+    pub fn version_get(&mut self) -> Reply {
+        self.botnana.version_get()
+    }
+
+    /// config.slave.get
+    pub fn config_drive_get<A: Into<DriveTarget>>(&mut self, drive: A) -> Reply {
+        self.botnana.config_drive_get(drive)
+    }
+
+    /// config.motion.get
+    pub fn config_motion_get(&mut self) -> Reply {
+        self.botnana.config_motion_get()
+    }
+
+    /// config.group.get
+    pub fn config_group_get(&mut self, position: u32) -> Reply {
+        self.botnana.config_group_get(position)
+    }
+
+    /// config.axis.get
+    pub fn config_axis_get(&mut self, position: u32) -> Reply {
+        self.botnana.config_axis_get(position)
+    }
+}
+
+impl From<Botnana> for AsyncBotnana {
+    fn from(botnana: Botnana) -> AsyncBotnana {
+        AsyncBotnana::new(botnana)
+    }
+}
+
+/// `AsyncBotnana::connect` 回傳的 Future
+pub struct Connect {
+    botnana: Botnana,
+    started: bool,
+    /// 最近一次登記到 `connect_wakers` 的 waker
+    waker: Option<Waker>,
+}
+
+impl Connect {
+    /// 檢查連線結果
+    fn check(&self) -> Option<Result<()>> {
+        if self.botnana.is_connected() {
+            Some(Ok(()))
+        } else if !*self.botnana.is_connecting.lock().expect("Connect") {
+            Some(Err(self.botnana.last_error().unwrap_or(Error::NotConnected)))
+        } else {
+            None
+        }
+    }
+}
+
+impl Future for Connect {
+    type Output = Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        if !self.started {
+            self.started = true;
+            if self.botnana.is_connected() {
+                return Poll::Ready(Ok(()));
+            }
+            if let Err(e) = self.botnana.try_connect() {
+                return Poll::Ready(Err(e));
+            }
+        }
+        // 先登記 waker 再檢查，避免在兩者之間連線狀態改變而沒有被喚醒
+        // 已登記過時取代原本的 waker，不重複加入
+        {
+            let connect_wakers = self.botnana.connect_wakers.clone();
+            let mut wakers = connect_wakers.lock().expect("Connect");
+            let registered = self
+                .waker
+                .as_ref()
+                .and_then(|old| wakers.iter().position(|x| x.will_wake(old)));
+            match registered {
+                Some(i) => {
+                    if !wakers[i].will_wake(cx.waker()) {
+                        wakers[i] = cx.waker().clone();
+                    }
+                }
+                None => wakers.push(cx.waker().clone()),
+            }
+            self.waker = Some(cx.waker().clone());
+        }
+        match self.check() {
+            Some(x) => Poll::Ready(x),
+            None => Poll::Pending,
+        }
+    }
+}
+
+/// `AsyncBotnana::evaluate` 回傳的 Future
+pub struct Evaluate {
+    reply: Result<Reply>,
+}
+
+impl Future for Evaluate {
+    type Output = Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<()>> {
+        match self.reply {
+            Ok(ref mut reply) => match Pin::new(reply).poll(cx) {
+                Poll::Ready(x) => Poll::Ready(x.map(|_| ())),
+                Poll::Pending => Poll::Pending,
+            },
+            Err(ref e) => Poll::Ready(Err(e.clone())),
+        }
+    }
+}
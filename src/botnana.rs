@@ -1,6 +1,13 @@
-use data_pool::DataPool;
+use data_pool::{DataPool, Snapshot};
+use error::{Error, Result};
+#[cfg(feature = "esi")]
+use esi::EsiLibrary;
+use event::{ChangeHandler, SubscriptionId, Subscriber};
+use json_api::PendingRequests;
 use program::Program;
-use serde_json;
+use protocol::{self, tag_matches};
+use reconnect::ReconnectPolicy;
+use serde_json::Value;
 use std::{
     self,
     boxed::Box,
@@ -12,30 +19,33 @@ use std::{
         mpsc::{self, TryRecvError},
         Arc, Mutex,
     },
+    task::Waker,
     thread,
 };
 use url;
-use ws::{
-    self, connect, util::Token, CloseCode, Error, ErrorKind, Handler, Handshake, Message, Result,
-};
+use ws::{self, connect, util::Token, CloseCode, ErrorKind, Handler, Handshake, Message};
 const WS_TIMEOUT_TOKEN: Token = Token(1);
 const WS_WATCHDOG_PERIOD_MS: u64 = 25_000;
 const VERSION: &'static str = env!("CARGO_PKG_VERSION");
 
 /// Callback Handler
 struct CallbackHandler {
+    /// 用來移除 callback 的 id
+    id: SubscriptionId,
     /// 執行次數
     count: u32,
     /// 用來回傳指標給使用者
     pointer: *mut c_void,
     /// callback 函式指標
-    callback: Box<dyn Fn(*mut c_void, *const c_char) + Send>,
+    callback: Arc<dyn Fn(*mut c_void, *const c_char) + Send>,
 }
 
 unsafe impl Send for CallbackHandler {}
 
 /// Callback Handler for tag
 struct TagCallbackHandler {
+    /// 用來移除 callback 的 id
+    id: SubscriptionId,
     /// 執行次數
     count: u32,
     /// 用來回傳指標給使用者
@@ -45,11 +55,30 @@ struct TagCallbackHandler {
     /// u32: position
     /// u32: channel
     /// u32: value (string)
-    callback: Box<dyn Fn(*mut c_void, u32, u32, *const c_char) + Send>,
+    callback: Arc<dyn Fn(*mut c_void, u32, u32, *const c_char) + Send>,
 }
 
 unsafe impl Send for TagCallbackHandler {}
 
+/// Callback Handler for tag pattern
+struct PatternCallbackHandler {
+    /// glob pattern
+    pattern: String,
+    /// 用來移除 callback 的 id
+    id: SubscriptionId,
+    /// 執行次數
+    count: u32,
+    /// 用來回傳指標給使用者
+    pointer: *mut c_void,
+    /// callback 函式指標
+    /// *mut c_void: 使用者設定的指標
+    /// *const c_char: tag
+    /// *const c_char: value (string)
+    callback: Arc<dyn Fn(*mut c_void, *const c_char, *const c_char) + Send>,
+}
+
+unsafe impl Send for PatternCallbackHandler {}
+
 /// Botnana
 #[repr(C)]
 #[derive(Clone)]
@@ -60,6 +89,13 @@ pub struct Botnana {
     ws_out: Arc<Mutex<Option<ws::Sender>>>,
     tag_handlers: Arc<Mutex<HashMap<String, Vec<CallbackHandler>>>>,
     tagname_handlers: Arc<Mutex<HashMap<String, Vec<TagCallbackHandler>>>>,
+    pattern_handlers: Arc<Mutex<Vec<PatternCallbackHandler>>>,
+    /// DriveChange 的 callbacks
+    pub(crate) change_handlers: Arc<Mutex<Vec<ChangeHandler>>>,
+    /// 下一個 callback 的 id
+    next_subscription_id: Arc<Mutex<u64>>,
+    /// tag event 的訂閱者
+    pub(crate) subscribers: Arc<Mutex<Vec<Subscriber>>>,
     /// 用來存放 forth 命令的 buffer
     scripts_buffer: Arc<Mutex<VecDeque<String>>>,
     /// 在 polling thread裡，每次從 scripts buffer 裡拿出 scripts_pop_count 個暫存命令送出給 Botnana motion server
@@ -67,18 +103,41 @@ pub struct Botnana {
     /// poll thread 啟動的時間
     poll_interval_ms: Arc<Mutex<u64>>,
     is_connected: Arc<Mutex<bool>>,
-    is_connecting: Arc<Mutex<bool>>,
+    pub(crate) is_connecting: Arc<Mutex<bool>>,
     on_open_cb: Arc<Mutex<Option<CallbackHandler>>>,
     on_error_cb: Arc<Mutex<Option<CallbackHandler>>>,
     on_send_cb: Arc<Mutex<Option<CallbackHandler>>>,
     on_message_cb: Arc<Mutex<Option<CallbackHandler>>>,
+    on_reconnect_cb: Arc<Mutex<Option<CallbackHandler>>>,
+    /// Data pool，處理訊息時逐筆更新，讀取時可能只套用了訊息的一部分，
+    /// 需要一致的資料時請使用 `snapshot()`
     pub data_pool: Arc<Mutex<DataPool>>,
+    /// 最近一次完整套用訊息後的 data pool
+    pub(crate) snapshot: Arc<Mutex<Arc<Snapshot>>>,
     pub(crate) internal_handlers:
-        Arc<Mutex<HashMap<String, Box<dyn Fn(&mut DataPool, usize, usize, &str) + Send>>>>,
+        Arc<Mutex<HashMap<String, Box<dyn Fn(&mut DataPool, usize, usize, &str) -> bool + Send>>>>,
     pub(crate) init_queries: Arc<Mutex<Vec<String>>>,
     pub(crate) cyclic_queries: Arc<Mutex<Vec<String>>>,
-    last_query: Arc<Mutex<usize>>,
+    pub(crate) last_query: Arc<Mutex<usize>>,
     query_count: Arc<Mutex<usize>>,
+    /// 下一個 JSON-RPC request id
+    pub(crate) next_request_id: Arc<Mutex<u64>>,
+    /// 等待回應中的 JSON-RPC requests
+    pub(crate) pending_requests: Arc<Mutex<PendingRequests>>,
+    /// 正在執行的 `try_evaluate` request id
+    pub(crate) evaluating: Arc<Mutex<Option<u64>>>,
+    /// 最後一次發生的錯誤
+    last_error: Arc<Mutex<Option<Error>>>,
+    /// 自動重新連線的設定
+    pub(crate) reconnect_policy: Arc<Mutex<Option<ReconnectPolicy>>>,
+    pub(crate) is_reconnecting: Arc<Mutex<bool>>,
+    /// 使用者呼叫 disconnect 中斷連線，此時不自動重新連線
+    pub(crate) manual_disconnect: Arc<Mutex<bool>>,
+    /// 非同步等待連線結果的 tasks
+    pub(crate) connect_wakers: Arc<Mutex<Vec<Waker>>>,
+    /// 用來檢查 SDO 的 ESI library
+    #[cfg(feature = "esi")]
+    pub(crate) esi: Arc<Mutex<Option<Arc<EsiLibrary>>>>,
 }
 
 impl Botnana {
@@ -91,6 +150,10 @@ impl Botnana {
             ws_out: Arc::new(Mutex::new(None)),
             tag_handlers: Arc::new(Mutex::new(HashMap::new())),
             tagname_handlers: Arc::new(Mutex::new(HashMap::new())),
+            pattern_handlers: Arc::new(Mutex::new(Vec::new())),
+            change_handlers: Arc::new(Mutex::new(Vec::new())),
+            next_subscription_id: Arc::new(Mutex::new(0)),
+            subscribers: Arc::new(Mutex::new(Vec::new())),
             scripts_buffer: Arc::new(Mutex::new(VecDeque::with_capacity(1024))),
             scripts_pop_count: Arc::new(Mutex::new(8)),
             poll_interval_ms: Arc::new(Mutex::new(10)),
@@ -100,12 +163,24 @@ impl Botnana {
             on_error_cb: Arc::new(Mutex::new(None)),
             on_send_cb: Arc::new(Mutex::new(None)),
             on_message_cb: Arc::new(Mutex::new(None)),
+            on_reconnect_cb: Arc::new(Mutex::new(None)),
             data_pool: Arc::new(Mutex::new(DataPool::new())),
+            snapshot: Arc::new(Mutex::new(Arc::new(DataPool::new().snapshot()))),
             internal_handlers: Arc::new(Mutex::new(HashMap::new())),
             init_queries: Arc::new(Mutex::new(Vec::new())),
             cyclic_queries: Arc::new(Mutex::new(Vec::new())),
             last_query: Arc::new(Mutex::new(0)),
             query_count: Arc::new(Mutex::new(3)),
+            next_request_id: Arc::new(Mutex::new(0)),
+            pending_requests: Arc::new(Mutex::new(HashMap::new())),
+            evaluating: Arc::new(Mutex::new(None)),
+            last_error: Arc::new(Mutex::new(None)),
+            reconnect_policy: Arc::new(Mutex::new(None)),
+            is_reconnecting: Arc::new(Mutex::new(false)),
+            manual_disconnect: Arc::new(Mutex::new(false)),
+            connect_wakers: Arc::new(Mutex::new(Vec::new())),
+            #[cfg(feature = "esi")]
+            esi: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -153,15 +228,21 @@ impl Botnana {
         *self.is_connected.lock().expect("")
     }
 
+    /// Last error (最後一次透過 on_error callback 回報的錯誤)
+    pub fn last_error(&self) -> Option<Error> {
+        self.last_error.lock().expect("last_error").clone()
+    }
+
     /// Set on_open callback
     pub fn set_on_open_cb<F>(&mut self, pointer: *mut c_void, cb: F)
     where
         F: Fn(*mut c_void, *const c_char) + Send + 'static,
     {
         *self.on_open_cb.lock().expect("set_on_open_cb") = Some(CallbackHandler {
+            id: SubscriptionId::NONE,
             count: 0,
             pointer: pointer,
-            callback: Box::new(cb),
+            callback: Arc::new(cb),
         });
     }
 
@@ -171,20 +252,31 @@ impl Botnana {
         F: Fn(*mut c_void, *const c_char) + Send + 'static,
     {
         *self.on_error_cb.lock().expect("set_on_error_cb") = Some(CallbackHandler {
+            id: SubscriptionId::NONE,
             count: 0,
             pointer: pointer,
-            callback: Box::new(cb),
+            callback: Arc::new(cb),
         });
     }
 
     /// Connect to botnana
     pub fn connect(&mut self) {
+        if let Err(e) = self.try_connect() {
+            self.execute_on_error_cb(e);
+        }
+    }
+
+    /// Connect to botnana
+    /// 連線的結果會透過 on_open 或 on_error callback 通知，
+    /// 此處只回傳無法建立連線 thread 的錯誤
+    pub fn try_connect(&mut self) -> Result<()> {
         // 如果已經在等待連線就跳出
         if *self.is_connecting.lock().expect("connecting") {
-            return;
+            return Ok(());
         } else {
             *self.is_connecting.lock().expect("connecting") = true;
         }
+        *self.manual_disconnect.lock().expect("connecting") = false;
 
         // 從 user thread 送到 ws client thread，將指令透過 ws client thread 送到 motion server
         let (user_sender, client_receiver) = mpsc::channel();
@@ -216,6 +308,7 @@ impl Botnana {
                                 sender: client_sender.clone(),
                                 thread_tx: thread_tx.clone(),
                                 on_error_cb: bna.on_error_cb.clone(),
+                                last_error: bna.last_error.clone(),
                                 is_watchdog_refreshed: false,
                             });
                             // 直到 WS Client Event loop 結束， 才會執行以下程式。
@@ -227,10 +320,16 @@ impl Botnana {
                                 .clear();
                             *bna.is_connecting.lock().expect("Exit WS Event Loop") = false;
                             *bna.is_connected.lock().expect("Exit WS Event Loop") = false;
+                            // 連線已中斷，不會再收到回應
+                            bna.cancel_requests();
+                            bna.wake_connect_waiters();
+                            bna.schedule_reconnect();
                         })
                 {
-                    botnana
-                        .execute_on_error_cb(&format!("Can't create WS CLIENT thread ({})\n", e));
+                    botnana.execute_on_error_cb(Error::Connection(format!(
+                        "Can't create WS CLIENT thread ({})",
+                        e
+                    )));
                 }
 
                 // 等待 WS 連線後，將 ws_sender 回傳
@@ -272,10 +371,10 @@ impl Botnana {
                             }
                         })
                     {
-                        botnana.execute_on_error_cb(&format!(
-                            "Can't create MESSAGE_PROCESSOR thread ({})\n",
+                        botnana.execute_on_error_cb(Error::Connection(format!(
+                            "Can't create MESSAGE_PROCESSOR thread ({})",
                             e
-                        ));
+                        )));
                     }
 
                     // poll thread
@@ -318,18 +417,24 @@ impl Botnana {
                                 }
                             })
                     {
-                        botnana.execute_on_error_cb(&format!("Can't create POLL thread ({})\n", e));
+                        botnana.execute_on_error_cb(Error::Connection(format!(
+                            "Can't create POLL thread ({})",
+                            e
+                        )));
                     }
                     *botnana.is_connected.lock().expect("Exit WS Event Loop") = true;
                     // 建制成功後呼叫 on_open callback
                     botnana.execute_on_open_cb();
+                    botnana.wake_connect_waiters();
                 }
             })
-            .expect("Create Try Connection Thread");
+            .map(|_| ())
+            .map_err(|e| Error::Connection(format!("Can't create Try Connection thread ({})", e)))
     }
 
     /// Disconnect
     pub fn disconnect(&mut self) {
+        *self.manual_disconnect.lock().expect("disconnect") = true;
         if let Some(ref mut ws_out) = *self.ws_out.lock().expect("disconnect") {
             let _ = ws_out.close(CloseCode::Normal);
         }
@@ -337,48 +442,55 @@ impl Botnana {
 
     /// Send message to mpsc channel
     pub fn send_message(&mut self, msg: &str) {
-        if self.has_ws_sender() {
-            self.execute_on_send_cb(msg);
-            let mut error_info = Ok(());
-            if let Some(ref sender) = *self.user_sender.lock().expect("send message") {
-                error_info = sender.send(Message::Text(msg.to_string()));
-            }
+        if let Err(e @ Error::SendFailed(_)) = self.try_send_message(msg) {
+            self.execute_on_error_cb(e);
+        }
+    }
 
-            if let Err(e) = error_info {
-                self.execute_on_error_cb(&format!("Send Message Error: {}\n", e));
-            }
+    /// Send message to mpsc channel
+    pub fn try_send_message(&mut self, msg: &str) -> Result<()> {
+        if !self.has_ws_sender() {
+            return Err(Error::NotConnected);
+        }
+        self.execute_on_send_cb(msg);
+        match *self.user_sender.lock().expect("send message") {
+            Some(ref sender) => sender
+                .send(Message::Text(msg.to_string()))
+                .map_err(|e| Error::SendFailed(e.to_string())),
+            None => Err(Error::NotConnected),
         }
     }
 
     /// Evaluate (立即送出)
     pub fn evaluate(&mut self, script: &str) {
-        if self.has_ws_sender() {
-            if let Ok(x) = serde_json::to_value(script) {
-                let msg = r#"{"jsonrpc":"2.0","method":"script.evaluate","params":{"script":"#
-                    .to_owned()
-                    + &x.to_string()
-                    + r#"}}"#;
-                self.execute_on_send_cb(&msg);
-                let mut error_info = Ok(());
-                if let Some(ref sender) = *self.user_sender.lock().expect("evaluate") {
-                    error_info = sender.send(Message::Text(msg.to_string()));
-                }
-
-                if let Err(e) = error_info {
-                    self.execute_on_error_cb(&format!("Send Message Error: {}\n", e));
-                }
-            }
+        if let Err(e @ Error::SendFailed(_)) = self.send_evaluate(script) {
+            self.execute_on_error_cb(e);
         }
     }
 
+    /// Evaluate (立即送出)，未連線或送出失敗時回傳錯誤
+    pub(crate) fn send_evaluate(&mut self, script: &str) -> Result<()> {
+        let msg = r#"{"jsonrpc":"2.0","method":"script.evaluate","params":{"script":"#.to_owned()
+            + &Value::from(script).to_string()
+            + r#"}}"#;
+        self.try_send_message(&msg)
+    }
+
     /// Send script to command buffer （將命令送到緩衝區）
     pub fn send_script_to_buffer(&mut self, script: &str) {
-        if self.has_ws_sender() {
-            self.scripts_buffer
-                .lock()
-                .expect("")
-                .push_back(script.to_owned() + "\n");
+        let _ = self.try_send_script_to_buffer(script);
+    }
+
+    /// Send script to command buffer （將命令送到緩衝區）
+    pub fn try_send_script_to_buffer(&mut self, script: &str) -> Result<()> {
+        if !self.has_ws_sender() {
+            return Err(Error::NotConnected);
         }
+        self.scripts_buffer
+            .lock()
+            .expect("")
+            .push_back(script.to_owned() + "\n");
+        Ok(())
     }
 
     /// Set poll interval_ms
@@ -472,7 +584,7 @@ impl Botnana {
 
     /// Handle message
     /// 處理 server 送過來的訊息
-    fn handle_message(&mut self, message: &str) {
+    pub(crate) fn handle_message(&mut self, message: &str) {
         if message.len() > 0 {
             if let Some(ref cb) = *self.on_message_cb.lock().unwrap() {
                 let mut temp_msg = String::from(message).into_bytes();
@@ -487,115 +599,150 @@ impl Botnana {
                 (cb.callback)(cb.pointer, msg);
             }
         }
+        // JSON-RPC 的回應不是 tag|value 格式
+        if self.handle_response(message) {
+            return;
+        }
+        let mut updated = false;
+        // 處理內部要求的訊息，整個訊息套用到 data pool 後才執行 callbacks
         {
-            let lines: Vec<&str> = message.split("\n").collect();
-            let mut tagname_handlers = self.tagname_handlers.lock().expect("self.handlers.lock()");
-            let mut tag_handlers = self.tag_handlers.lock().expect("self.handlers.lock()");
             let internal_handlers = self
                 .internal_handlers
                 .lock()
                 .expect("self.internal_handles.lock()");
+            let mut data_pool = self.data_pool.lock().expect("self.data_pool.lock()");
+            for record in protocol::parse(message) {
+                // 從站數改變後還沒重新初始化時，忽略超出範圍的從站資料
+                if record.position() > 0 && record.position() >= data_pool.slaves.len() {
+                    continue;
+                }
+                if let Some(handler) = internal_handlers.get(record.name) {
+                    // 只有資料改變時才更新 generation 並發布新的快照
+                    updated |= handler(
+                        &mut data_pool,
+                        record.position(),
+                        record.channel(),
+                        record.value,
+                    );
+                }
+            }
+        }
 
-            for line in lines {
-                // 用 `|` 解析訊息
-                let r: Vec<&str> = line.split("|").collect();
-                let mut next_tag = true;
-                let mut event = "";
-                for e in r {
-                    if next_tag {
-                        event = e.trim_start();
-                        next_tag = false;
-                    } else {
-                        // 處理內部要求的訊息
-                        // 先將 event 解析成 tag_name.x.x
-                        let tag: Vec<&str> = event.split(".").collect();
-                        // internal_handlers
-                        if let Some(handler) = internal_handlers.get(tag[0]) {
-                            let mut data_pool =
-                                self.data_pool.lock().expect("self.internal_handles.lock()");
-                            let mut tag_index: [usize; 2] = [0; 2];
-                            let len = tag.len().min(3);
-                            for i in 1..len {
-                                if let Ok(x) = tag[i].parse::<usize>() {
-                                    tag_index[len - i - 1] = x;
-                                }
-                            }
-                            handler(&mut data_pool, tag_index[0], tag_index[1], e);
-                        }
+        for record in protocol::parse(message) {
+            let position = record.position() as u32;
+            let channel = record.channel() as u32;
+            self.handle_evaluation_event(&record);
 
-                        let mut remove_event = false;
-                        if let Some(handler) = tagname_handlers.get_mut(tag[0]) {
-                            // tag_index[0]: position
-                            // tag_index[1]: channel
-                            let mut tag_index: [u32; 2] = [0; 2];
-                            let len = tag.len().min(3);
-                            for i in 1..len {
-                                if let Ok(x) = tag[i].parse::<u32>() {
-                                    tag_index[len - i - 1] = x;
-                                }
-                            }
-                            // 轉換字串型態
-                            let mut msg = String::from(e).into_bytes();
-                            msg.push(0);
-                            let msg = CStr::from_bytes_with_nul(msg.as_slice())
-                                .expect("toCstr")
-                                .as_ptr();
-                            // 執行對應的 callback function
-                            // 使用 rev() 是為了 handler.remove，從後面刪除才不會影響 i 對應 vec 內的成員
-                            for i in (0..handler.len()).rev() {
-                                (handler[i].callback)(
-                                    handler[i].pointer,
-                                    tag_index[0],
-                                    tag_index[1],
-                                    msg,
-                                );
+            // 轉換字串型態
+            let mut tag = String::from(record.tag).into_bytes();
+            tag.push(0);
+            let tag = CStr::from_bytes_with_nul(tag.as_slice())
+                .expect("toCstr")
+                .as_ptr();
+            let mut msg = String::from(record.value).into_bytes();
+            msg.push(0);
+            let msg = CStr::from_bytes_with_nul(msg.as_slice())
+                .expect("toCstr")
+                .as_ptr();
 
-                                if handler[i].count > 0 {
-                                    handler[i].count -= 1;
-                                    if handler[i].count == 0 {
-                                        handler.remove(i);
-                                    }
-                                }
+            // 先在鎖住 handlers 時取出要執行的 callbacks，解鎖後才執行，
+            // 所以 callback 內可以呼叫 remove_callback 或設定新的 callback
+            let mut tagname_callbacks = Vec::new();
+            {
+                let mut tagname_handlers = self.tagname_handlers.lock().expect("handle_message");
+                let mut remove_event = false;
+                if let Some(handler) = tagname_handlers.get_mut(record.name) {
+                    // 使用 rev() 是為了 handler.remove，從後面刪除才不會影響 i 對應 vec 內的成員
+                    for i in (0..handler.len()).rev() {
+                        tagname_callbacks.push((handler[i].callback.clone(), handler[i].pointer));
+                        if handler[i].count > 0 {
+                            handler[i].count -= 1;
+                            if handler[i].count == 0 {
+                                handler.remove(i);
                             }
-                            remove_event = handler.len() == 0;
-                        }
-                        // 假如都沒有 handle 就將此事件刪除
-                        if remove_event {
-                            tagname_handlers.remove(tag[0]);
                         }
+                    }
+                    remove_event = handler.len() == 0;
+                }
+                // 假如都沒有 handle 就將此事件刪除
+                if remove_event {
+                    tagname_handlers.remove(record.name);
+                }
+            }
 
-                        remove_event = false;
-                        if let Some(handler) = tag_handlers.get_mut(event) {
-                            // 轉換字串型態
-                            let mut msg = String::from(e).into_bytes();
-                            msg.push(0);
-                            let msg = CStr::from_bytes_with_nul(msg.as_slice())
-                                .expect("toCstr")
-                                .as_ptr();
-                            // 執行對應的 callback function
-                            // 使用 rev() 是為了 handler.remove，從後面刪除才不會影響 i 對應 vec 內的成員
-                            for i in (0..handler.len()).rev() {
-                                (handler[i].callback)(handler[i].pointer, msg);
-
-                                if handler[i].count > 0 {
-                                    handler[i].count -= 1;
-                                    if handler[i].count == 0 {
-                                        handler.remove(i);
-                                    }
-                                }
+            let mut tag_callbacks = Vec::new();
+            {
+                let mut tag_handlers = self.tag_handlers.lock().expect("handle_message");
+                let mut remove_event = false;
+                if let Some(handler) = tag_handlers.get_mut(record.tag) {
+                    // 使用 rev() 是為了 handler.remove，從後面刪除才不會影響 i 對應 vec 內的成員
+                    for i in (0..handler.len()).rev() {
+                        tag_callbacks.push((handler[i].callback.clone(), handler[i].pointer));
+                        if handler[i].count > 0 {
+                            handler[i].count -= 1;
+                            if handler[i].count == 0 {
+                                handler.remove(i);
                             }
-                            remove_event = handler.len() == 0;
                         }
-                        // 假如都沒有 handle 就將此事件刪除
-                        if remove_event {
-                            tag_handlers.remove(event);
+                    }
+                    remove_event = handler.len() == 0;
+                }
+                // 假如都沒有 handle 就將此事件刪除
+                if remove_event {
+                    tag_handlers.remove(record.tag);
+                }
+            }
+
+            let mut pattern_callbacks = Vec::new();
+            {
+                let mut pattern_handlers = self.pattern_handlers.lock().expect("handle_message");
+                // 使用 rev() 是為了 pattern_handlers.remove，從後面刪除才不會影響 i 對應 vec 內的成員
+                for i in (0..pattern_handlers.len()).rev() {
+                    if !tag_matches(&pattern_handlers[i].pattern, record.tag) {
+                        continue;
+                    }
+                    pattern_callbacks.push((
+                        pattern_handlers[i].callback.clone(),
+                        pattern_handlers[i].pointer,
+                    ));
+                    if pattern_handlers[i].count > 0 {
+                        pattern_handlers[i].count -= 1;
+                        if pattern_handlers[i].count == 0 {
+                            pattern_handlers.remove(i);
                         }
-                        next_tag = true;
                     }
                 }
             }
+
+            // 執行對應的 callback function
+            for (callback, pointer) in tagname_callbacks {
+                callback(pointer, position, channel, msg);
+            }
+            for (callback, pointer) in tag_callbacks {
+                callback(pointer, msg);
+            }
+            for (callback, pointer) in pattern_callbacks {
+                callback(pointer, tag, msg);
+            }
+
+            // 送給訂閱者
+            self.publish_tag_event(&record);
         }
-        self.data_pool_forth();
+        updated |= self.data_pool_forth();
+        if updated {
+            self.publish_snapshot();
+        }
+        self.publish_drive_changes();
+    }
+
+    /// 配置新的 callback id
+    pub(crate) fn new_subscription_id(&self) -> SubscriptionId {
+        let mut next_id = self
+            .next_subscription_id
+            .lock()
+            .expect("new_subscription_id");
+        *next_id += 1;
+        SubscriptionId(*next_id)
     }
 
     /// Set callback for tag
@@ -603,22 +750,27 @@ impl Botnana {
     /// `count` is handler called times
     /// `pointer` is user data pointer
     /// `handler` is user function
+    /// 回傳的 id 可以用 `remove_callback` 移除此 callback
     pub fn set_tag_callback<F>(
         &mut self,
         tag: &'static str,
         count: u32,
         pointer: *mut c_void,
         cb: F,
-    ) where
+    ) -> SubscriptionId
+    where
         F: Fn(*mut c_void, *const c_char) + Send + 'static,
     {
+        let id = self.new_subscription_id();
         let mut tag_handlers = self.tag_handlers.lock().unwrap();
         let handler = tag_handlers.entry(tag.to_owned()).or_insert(Vec::new());
         handler.push(CallbackHandler {
+            id,
             count: count,
             pointer: pointer,
-            callback: Box::new(cb),
+            callback: Arc::new(cb),
         });
+        id
     }
 
     /// Set callback for name of tag
@@ -626,24 +778,117 @@ impl Botnana {
     /// `count` is handler called times
     /// `pointer` is user data pointer
     /// `handler` is user function
+    /// 回傳的 id 可以用 `remove_callback` 移除此 callback
     pub fn set_tagname_callback<F>(
         &mut self,
         name: &'static str,
         count: u32,
         pointer: *mut c_void,
         cb: F,
-    ) where
+    ) -> SubscriptionId
+    where
         F: Fn(*mut c_void, u32, u32, *const c_char) + Send + 'static,
     {
+        let id = self.new_subscription_id();
         let mut tagname_handlers = self.tagname_handlers.lock().unwrap();
         let handler = tagname_handlers
             .entry(name.to_owned())
             .or_insert(Vec::new());
         handler.push(TagCallbackHandler {
+            id,
             count: count,
             pointer: pointer,
-            callback: Box::new(cb),
+            callback: Arc::new(cb),
         });
+        id
+    }
+
+    /// Set callback for tag pattern
+    /// `pattern` is glob pattern of tag (例如 `real_position.*.3`, `*.2.1`)，規則請見 `protocol::tag_matches`
+    /// `count` is handler called times
+    /// `pointer` is user data pointer
+    /// `handler` is user function, 收到完整的 tag 與 value
+    /// 回傳的 id 可以用 `remove_callback` 移除此 callback
+    pub fn set_tag_pattern_callback<F>(
+        &mut self,
+        pattern: &str,
+        count: u32,
+        pointer: *mut c_void,
+        cb: F,
+    ) -> SubscriptionId
+    where
+        F: Fn(*mut c_void, *const c_char, *const c_char) + Send + 'static,
+    {
+        let id = self.new_subscription_id();
+        self.pattern_handlers
+            .lock()
+            .expect("set_tag_pattern_callback")
+            .push(PatternCallbackHandler {
+                pattern: pattern.to_owned(),
+                id,
+                count,
+                pointer,
+                callback: Arc::new(cb),
+            });
+        id
+    }
+
+    /// Remove callback
+    /// 移除 `set_tag_callback`、`set_tagname_callback`、`set_tag_pattern_callback` 或 `on_drive_change`
+    /// 設定的 callback，找到並移除時回傳 true
+    /// 可以在 callback 內呼叫，已經取出要執行的 callback 仍會執行完這一次
+    pub fn remove_callback(&mut self, id: SubscriptionId) -> bool {
+        let mut removed = false;
+        {
+            let mut tag_handlers = self.tag_handlers.lock().expect("remove_callback");
+            for handler in tag_handlers.values_mut() {
+                let len = handler.len();
+                handler.retain(|x| x.id != id);
+                removed |= handler.len() != len;
+            }
+            tag_handlers.retain(|_, handler| !handler.is_empty());
+        }
+        {
+            let mut tagname_handlers = self.tagname_handlers.lock().expect("remove_callback");
+            for handler in tagname_handlers.values_mut() {
+                let len = handler.len();
+                handler.retain(|x| x.id != id);
+                removed |= handler.len() != len;
+            }
+            tagname_handlers.retain(|_, handler| !handler.is_empty());
+        }
+        {
+            let mut pattern_handlers = self.pattern_handlers.lock().expect("remove_callback");
+            let len = pattern_handlers.len();
+            pattern_handlers.retain(|x| x.id != id);
+            removed |= pattern_handlers.len() != len;
+        }
+        let mut change_handlers = self.change_handlers.lock().expect("remove_callback");
+        let len = change_handlers.len();
+        change_handlers.retain(|x| x.id != id);
+        removed || change_handlers.len() != len
+    }
+
+    /// Clear callbacks
+    /// 移除 `tag` (或 tag 名稱、pattern) 所有的 callback，回傳移除的數量
+    /// 可以在 callback 內呼叫
+    pub fn clear_callbacks(&mut self, tag: &str) -> usize {
+        let mut count = 0;
+        if let Some(handler) = self.tag_handlers.lock().expect("clear_callbacks").remove(tag) {
+            count += handler.len();
+        }
+        if let Some(handler) = self
+            .tagname_handlers
+            .lock()
+            .expect("clear_callbacks")
+            .remove(tag)
+        {
+            count += handler.len();
+        }
+        let mut pattern_handlers = self.pattern_handlers.lock().expect("clear_callbacks");
+        let len = pattern_handlers.len();
+        pattern_handlers.retain(|x| x.pattern != tag);
+        count + len - pattern_handlers.len()
     }
 
     /// Has WS sender ?
@@ -651,8 +896,21 @@ impl Botnana {
         self.ws_out.lock().expect("has_ws_sender").is_some()
     }
 
+    /// 連線狀態改變，喚醒等待連線結果的 tasks
+    fn wake_connect_waiters(&self) {
+        let wakers: Vec<Waker> = self
+            .connect_wakers
+            .lock()
+            .expect("wake_connect_waiters")
+            .drain(..)
+            .collect();
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
     /// Execute on_error callback
-    fn execute_on_error_cb(&mut self, msg: &str) {
+    fn execute_on_error_cb(&mut self, err: Error) {
         *self.is_connecting.lock().expect("execute_on_error_cb") = false;
         *self.is_connected.lock().expect("execute_on_error_cb") = false;
         *self.user_sender.lock().expect("execute_on_error_cb") = None;
@@ -661,8 +919,16 @@ impl Botnana {
             .lock()
             .expect("execute_on_error_cb")
             .clear();
-        if let Some(ref cb) = *self.on_error_cb.lock().expect("execute_on_error_cb") {
-            let mut temp_msg = String::from(msg).into_bytes();
+        self.report_error(err);
+        self.wake_connect_waiters();
+    }
+
+    /// 記錄錯誤並呼叫 on_error callback，不影響連線狀態
+    pub(crate) fn report_error(&self, err: Error) {
+        let msg = format!("{}\n", err);
+        *self.last_error.lock().expect("report_error") = Some(err);
+        if let Some(ref cb) = *self.on_error_cb.lock().expect("report_error") {
+            let mut temp_msg = msg.into_bytes();
             temp_msg.push(0);
             let msg = CStr::from_bytes_with_nul(temp_msg.as_slice())
                 .expect("toCstr")
@@ -702,9 +968,10 @@ impl Botnana {
         F: Fn(*mut c_void, *const c_char) + Send + 'static,
     {
         *self.on_send_cb.lock().unwrap() = Some(CallbackHandler {
+            id: SubscriptionId::NONE,
             count: 0,
             pointer: pointer,
-            callback: Box::new(cb),
+            callback: Arc::new(cb),
         });
     }
 
@@ -713,12 +980,39 @@ impl Botnana {
         F: Fn(*mut c_void, *const c_char) + Send + 'static,
     {
         *self.on_message_cb.lock().unwrap() = Some(CallbackHandler {
+            id: SubscriptionId::NONE,
             count: 0,
             pointer: pointer,
-            callback: Box::new(cb),
+            callback: Arc::new(cb),
+        });
+    }
+
+    /// Set on_reconnect callback
+    /// 重新連線開始、成功或放棄時呼叫
+    pub fn set_on_reconnect_cb<F>(&mut self, pointer: *mut c_void, cb: F)
+    where
+        F: Fn(*mut c_void, *const c_char) + Send + 'static,
+    {
+        *self.on_reconnect_cb.lock().expect("set_on_reconnect_cb") = Some(CallbackHandler {
+            id: SubscriptionId::NONE,
+            count: 0,
+            pointer,
+            callback: Arc::new(cb),
         });
     }
 
+    /// Execute on_reconnect callback
+    pub(crate) fn execute_on_reconnect_cb(&self, msg: &str) {
+        if let Some(ref cb) = *self.on_reconnect_cb.lock().expect("execute_on_reconnect_cb") {
+            let mut temp_msg = String::from(msg).into_bytes();
+            temp_msg.push(0);
+            let msg = CStr::from_bytes_with_nul(temp_msg.as_slice())
+                .expect("toCstr")
+                .as_ptr();
+            (cb.callback)(cb.pointer, msg);
+        }
+    }
+
     /// Abort porgram
     pub fn abort_program(&mut self) {
         self.evaluate(r#"abort-program"#);
@@ -753,14 +1047,17 @@ struct Client {
     sender: mpsc::Sender<String>,
     thread_tx: mpsc::Sender<ws::Sender>,
     on_error_cb: Arc<Mutex<Option<CallbackHandler>>>,
+    last_error: Arc<Mutex<Option<Error>>>,
     is_watchdog_refreshed: bool,
 }
 
 impl Client {
     /// Execute on_error callback
-    fn execute_on_error_cb(&self, msg: &str) {
+    fn execute_on_error_cb(&self, err: Error) {
+        let msg = format!("{}\n", err);
+        *self.last_error.lock().expect("execute_on_error_cb") = Some(err);
         if let Some(ref cb) = *self.on_error_cb.lock().expect("execute_on_error_cb") {
-            let mut temp_msg = String::from(msg).into_bytes();
+            let mut temp_msg = msg.into_bytes();
             temp_msg.push(0);
             let msg = CStr::from_bytes_with_nul(temp_msg.as_slice())
                 .expect("toCstr")
@@ -772,11 +1069,11 @@ impl Client {
 
 impl Handler for Client {
     /// on_open
-    fn on_open(&mut self, _: Handshake) -> Result<()> {
+    fn on_open(&mut self, _: Handshake) -> ws::Result<()> {
         self.ws_out
             .timeout(WS_WATCHDOG_PERIOD_MS, WS_TIMEOUT_TOKEN)?;
         self.thread_tx.send(self.ws_out.clone()).map_err(|err| {
-            Error::new(
+            ws::Error::new(
                 ErrorKind::Internal,
                 format!("Unable to communicate between threads: {:?}.", err),
             )
@@ -784,7 +1081,7 @@ impl Handler for Client {
     }
 
     /// on_message
-    fn on_message(&mut self, msg: Message) -> Result<()> {
+    fn on_message(&mut self, msg: Message) -> ws::Result<()> {
         self.is_watchdog_refreshed = true;
         if let Message::Text(m) = msg {
             // 資料長度 > 0 送進 mpsc::channel
@@ -798,25 +1095,31 @@ impl Handler for Client {
     }
 
     /// on error
-    fn on_error(&mut self, err: Error) {
-        self.execute_on_error_cb(&format!("on_error ({}): {:?}\n", VERSION, err));
+    fn on_error(&mut self, err: ws::Error) {
+        self.execute_on_error_cb(Error::Connection(format!(
+            "on_error ({}): {:?}",
+            VERSION, err
+        )));
     }
 
     /// on close
     fn on_close(&mut self, code: CloseCode, reason: &str) {
-        self.execute_on_error_cb(&format!(
-            "WS Client close code ({}) = {:?}, reason = {}\n",
+        self.execute_on_error_cb(Error::Connection(format!(
+            "WS Client close code ({}) = {:?}, reason = {}",
             VERSION, code, reason
-        ));
+        )));
     }
 
     /// Called when a timeout is triggered.
-    fn on_timeout(&mut self, _event: Token) -> Result<()> {
+    fn on_timeout(&mut self, _event: Token) -> ws::Result<()> {
         if !self.is_watchdog_refreshed {
             let result = self
                 .ws_out
                 .close_with_reason(CloseCode::Abnormal, "WS Client timeout");
-            self.execute_on_error_cb(&format!("WS Client timeout! ({})\n", VERSION));
+            self.execute_on_error_cb(Error::Connection(format!(
+                "WS Client timeout! ({})",
+                VERSION
+            )));
             result
         } else {
             self.is_watchdog_refreshed = false;
@@ -0,0 +1,194 @@
+use botnana::Botnana;
+use event::{SubscriptionId, TagEvent};
+use protocol::Record;
+use std::{
+    ffi::CStr,
+    os::raw::{c_char, c_void},
+    ptr, str,
+};
+
+/// 將 callback 收到的 C 字串轉成 &str
+/// 這些字串都是由 Botnana 內部的 String 轉成，所以一定是 UTF-8
+fn to_str<'a>(msg: *const c_char) -> &'a str {
+    if msg.is_null() {
+        return "";
+    }
+    unsafe { str::from_utf8(CStr::from_ptr(msg).to_bytes()).unwrap_or("") }
+}
+
+/// Rust closure 版本的 callback 設定，不需要處理 `*mut c_void` 與 `*const c_char`
+/// 需要保存的狀態請由 closure 捕捉
+impl Botnana {
+    /// Set on_open callback
+    pub fn on_open<F>(&mut self, cb: F)
+    where
+        F: Fn(&str) + Send + 'static,
+    {
+        self.set_on_open_cb(ptr::null_mut(), move |_: *mut c_void, msg| cb(to_str(msg)));
+    }
+
+    /// Set on_error callback
+    /// 錯誤的類型可以由 `last_error` 取得
+    pub fn on_error<F>(&mut self, cb: F)
+    where
+        F: Fn(&str) + Send + 'static,
+    {
+        self.set_on_error_cb(ptr::null_mut(), move |_: *mut c_void, msg| cb(to_str(msg)));
+    }
+
+    /// Set on_send callback
+    pub fn on_send<F>(&mut self, cb: F)
+    where
+        F: Fn(&str) + Send + 'static,
+    {
+        self.set_on_send_cb(ptr::null_mut(), move |_: *mut c_void, msg| cb(to_str(msg)));
+    }
+
+    /// Set on_message callback
+    pub fn on_message<F>(&mut self, cb: F)
+    where
+        F: Fn(&str) + Send + 'static,
+    {
+        self.set_on_message_cb(ptr::null_mut(), move |_: *mut c_void, msg| cb(to_str(msg)));
+    }
+
+    /// Set on_reconnect callback
+    pub fn on_reconnect<F>(&mut self, cb: F)
+    where
+        F: Fn(&str) + Send + 'static,
+    {
+        self.set_on_reconnect_cb(ptr::null_mut(), move |_: *mut c_void, msg| cb(to_str(msg)));
+    }
+
+    /// Set callback for tag
+    /// `tag` is tag
+    /// `count` is handler called times (0 表示不限次數)
+    /// `cb` 收到 tag 的值
+    pub fn on_tag<F>(&mut self, tag: &'static str, count: u32, cb: F) -> SubscriptionId
+    where
+        F: Fn(&str) + Send + 'static,
+    {
+        self.set_tag_callback(tag, count, ptr::null_mut(), move |_: *mut c_void, msg| {
+            cb(to_str(msg))
+        })
+    }
+
+    /// Set callback for name of tag
+    /// `name` is name of tag
+    /// `count` is handler called times (0 表示不限次數)
+    /// `cb` 收到 position, channel 與 tag 的值
+    pub fn on_tagname<F>(&mut self, name: &'static str, count: u32, cb: F) -> SubscriptionId
+    where
+        F: Fn(u32, u32, &str) + Send + 'static,
+    {
+        self.set_tagname_callback(
+            name,
+            count,
+            ptr::null_mut(),
+            move |_: *mut c_void, position, channel, msg| cb(position, channel, to_str(msg)),
+        )
+    }
+
+    /// Set callback for tag pattern
+    /// `pattern` is glob pattern of tag (例如 `real_position.*.3`)
+    /// `count` is handler called times (0 表示不限次數)
+    /// `cb` 收到解析後的 tag event
+    pub fn on_tag_pattern<F>(&mut self, pattern: &str, count: u32, cb: F) -> SubscriptionId
+    where
+        F: Fn(&TagEvent) + Send + 'static,
+    {
+        self.set_tag_pattern_callback(pattern, count, ptr::null_mut(), move |_, tag, msg| {
+            cb(&TagEvent::from(&Record::new(to_str(tag), to_str(msg))))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// 收集 callback 收到的值
+    fn collector() -> (Arc<Mutex<Vec<String>>>, impl Fn(&str) + Send + 'static) {
+        let values = Arc::new(Mutex::new(Vec::new()));
+        let sink = values.clone();
+        (values, move |value: &str| sink.lock().unwrap().push(value.to_owned()))
+    }
+
+    #[test]
+    fn tag_callback_count() {
+        let mut botnana = Botnana::new();
+        let (values, cb) = collector();
+        botnana.on_tag("version", 2, cb);
+        botnana.handle_message("version|1|version|2|version|3");
+        assert_eq!(*values.lock().unwrap(), vec!["1", "2"]);
+        // 執行次數用完就自動移除
+        assert_eq!(botnana.clear_callbacks("version"), 0);
+    }
+
+    #[test]
+    fn remove_callback_stops_delivery() {
+        let mut botnana = Botnana::new();
+        let (removed, cb) = collector();
+        let id = botnana.on_tag("version", 0, cb);
+        let (kept, cb) = collector();
+        botnana.on_tag("version", 0, cb);
+        let (pattern, cb) = collector();
+        let pattern_id = botnana.on_tag_pattern("real_position.*.2", 0, move |event| {
+            cb(&event.value)
+        });
+
+        botnana.handle_message("version|1|real_position.1.2|100");
+        assert!(botnana.remove_callback(id));
+        assert!(!botnana.remove_callback(id));
+        assert!(botnana.remove_callback(pattern_id));
+        botnana.handle_message("version|2|real_position.1.2|200");
+
+        assert_eq!(*removed.lock().unwrap(), vec!["1"]);
+        assert_eq!(*kept.lock().unwrap(), vec!["1", "2"]);
+        assert_eq!(*pattern.lock().unwrap(), vec!["100"]);
+    }
+
+    #[test]
+    fn clear_callbacks_by_tag() {
+        let mut botnana = Botnana::new();
+        let (values, cb) = collector();
+        botnana.on_tag("version", 0, cb);
+        let (_, cb) = collector();
+        botnana.on_tag("version", 0, cb);
+        let (_, cb) = collector();
+        botnana.on_tagname("real_position", 0, move |_, _, value| cb(value));
+        let (_, cb) = collector();
+        botnana.on_tag_pattern("real_position.*.2", 0, move |event| cb(&event.value));
+
+        assert_eq!(botnana.clear_callbacks("version"), 2);
+        assert_eq!(botnana.clear_callbacks("real_position"), 1);
+        assert_eq!(botnana.clear_callbacks("real_position.*.2"), 1);
+        assert_eq!(botnana.clear_callbacks("version"), 0);
+        botnana.handle_message("version|1");
+        assert!(values.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn remove_callback_inside_callback() {
+        let mut botnana = Botnana::new();
+        let id = Arc::new(Mutex::new(None));
+        let values = Arc::new(Mutex::new(Vec::new()));
+        let handle = botnana.clone();
+        let own_id = id.clone();
+        let sink = values.clone();
+        *id.lock().unwrap() = Some(botnana.on_tagname(
+            "real_position",
+            0,
+            move |position, channel, value| {
+                let mut botnana = handle.clone();
+                let removed = botnana.remove_callback(own_id.lock().unwrap().unwrap());
+                sink.lock().unwrap().push((position, channel, value.to_owned(), removed));
+            },
+        ));
+
+        botnana.handle_message("real_position.1.2|100");
+        botnana.handle_message("real_position.1.2|200");
+        assert_eq!(*values.lock().unwrap(), vec![(2, 1, "100".to_owned(), true)]);
+    }
+}
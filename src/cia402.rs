@@ -0,0 +1,141 @@
+use error::{Error, Result};
+use std::convert::TryFrom;
+
+/// CiA 402 驅動器狀態 (由 status word 0x6041 解碼)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Cia402State {
+    NotReadyToSwitchOn,
+    SwitchOnDisabled,
+    ReadyToSwitchOn,
+    SwitchedOn,
+    OperationEnabled,
+    QuickStopActive,
+    FaultReactionActive,
+    Fault,
+}
+
+impl Cia402State {
+    /// 依 CiA 402 的 status word 狀態表解碼 (bit 0 ~ 3, 5, 6)
+    pub fn from_status_word(status_word: u16) -> Cia402State {
+        if status_word & 0x004F == 0x0000 {
+            Cia402State::NotReadyToSwitchOn
+        } else if status_word & 0x004F == 0x0040 {
+            Cia402State::SwitchOnDisabled
+        } else if status_word & 0x006F == 0x0021 {
+            Cia402State::ReadyToSwitchOn
+        } else if status_word & 0x006F == 0x0023 {
+            Cia402State::SwitchedOn
+        } else if status_word & 0x006F == 0x0027 {
+            Cia402State::OperationEnabled
+        } else if status_word & 0x006F == 0x0007 {
+            Cia402State::QuickStopActive
+        } else if status_word & 0x004F == 0x000F {
+            Cia402State::FaultReactionActive
+        } else if status_word & 0x004F == 0x0008 {
+            Cia402State::Fault
+        } else {
+            // 不在狀態表內的組合，視為尚未準備好
+            Cia402State::NotReadyToSwitchOn
+        }
+    }
+}
+
+/// Status word bit 7: warning
+pub const STATUS_WARNING: u16 = 0x0080;
+/// Status word bit 10: target reached
+pub const STATUS_TARGET_REACHED: u16 = 0x0400;
+/// Status word bit 11: internal limit active
+pub const STATUS_INTERNAL_LIMIT: u16 = 0x0800;
+/// Status word bit 12: 依操作模式而定 (HM: homing attained, PP: set-point acknowledge)
+pub const STATUS_OP_MODE_BIT_12: u16 = 0x1000;
+/// Status word bit 13: 依操作模式而定 (HM: homing error, PP/CSP: following error)
+pub const STATUS_OP_MODE_BIT_13: u16 = 0x2000;
+
+/// CiA 402 operation mode (0x6060 / 0x6061)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OperationMode {
+    /// PP (位置模式)
+    ProfilePosition,
+    /// PV (速度模式)
+    ProfileVelocity,
+    /// TQ (扭力模式)
+    ProfileTorque,
+    /// HM (回歸原點)
+    Homing,
+    /// CSP (時間同步位置模式)
+    CyclicSyncPosition,
+    /// CSV (時間同步速度模式)
+    CyclicSyncVelocity,
+    /// CST (時間同步扭力模式)
+    CyclicSyncTorque,
+}
+
+impl From<OperationMode> for i8 {
+    fn from(mode: OperationMode) -> i8 {
+        match mode {
+            OperationMode::ProfilePosition => 1,
+            OperationMode::ProfileVelocity => 3,
+            OperationMode::ProfileTorque => 4,
+            OperationMode::Homing => 6,
+            OperationMode::CyclicSyncPosition => 8,
+            OperationMode::CyclicSyncVelocity => 9,
+            OperationMode::CyclicSyncTorque => 10,
+        }
+    }
+}
+
+impl TryFrom<i8> for OperationMode {
+    type Error = Error;
+
+    fn try_from(mode: i8) -> Result<OperationMode> {
+        match mode {
+            1 => Ok(OperationMode::ProfilePosition),
+            3 => Ok(OperationMode::ProfileVelocity),
+            4 => Ok(OperationMode::ProfileTorque),
+            6 => Ok(OperationMode::Homing),
+            8 => Ok(OperationMode::CyclicSyncPosition),
+            9 => Ok(OperationMode::CyclicSyncVelocity),
+            10 => Ok(OperationMode::CyclicSyncTorque),
+            _ => Err(Error::Parse(format!("Unknown operation mode: {}", mode))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_status_word() {
+        assert_eq!(Cia402State::from_status_word(0x0000), Cia402State::NotReadyToSwitchOn);
+        assert_eq!(Cia402State::from_status_word(0x0250), Cia402State::SwitchOnDisabled);
+        assert_eq!(Cia402State::from_status_word(0x0231), Cia402State::ReadyToSwitchOn);
+        assert_eq!(Cia402State::from_status_word(0x0233), Cia402State::SwitchedOn);
+        assert_eq!(Cia402State::from_status_word(0x1637), Cia402State::OperationEnabled);
+        assert_eq!(Cia402State::from_status_word(0x0217), Cia402State::QuickStopActive);
+        assert_eq!(Cia402State::from_status_word(0x021F), Cia402State::FaultReactionActive);
+        assert_eq!(Cia402State::from_status_word(0x0218), Cia402State::Fault);
+        // 不在狀態表內的組合
+        assert_eq!(Cia402State::from_status_word(0x0001), Cia402State::NotReadyToSwitchOn);
+    }
+
+    #[test]
+    fn decode_operation_mode() {
+        let modes = [
+            OperationMode::ProfilePosition,
+            OperationMode::ProfileVelocity,
+            OperationMode::ProfileTorque,
+            OperationMode::Homing,
+            OperationMode::CyclicSyncPosition,
+            OperationMode::CyclicSyncVelocity,
+            OperationMode::CyclicSyncTorque,
+        ];
+        for mode in modes.iter() {
+            assert_eq!(OperationMode::try_from(i8::from(*mode)), Ok(*mode));
+        }
+        assert_eq!(OperationMode::try_from(6), Ok(OperationMode::Homing));
+        assert!(OperationMode::try_from(0).is_err());
+        assert!(OperationMode::try_from(2).is_err());
+        assert!(OperationMode::try_from(-1).is_err());
+    }
+}
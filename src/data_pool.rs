@@ -1,18 +1,52 @@
+use address::{DriveAddr, DriveTarget, SlaveAddr};
+use al_state::{AlState, AL_STATUS_ERROR};
 use botnana::Botnana;
+use cia402::{
+    Cia402State, OperationMode, STATUS_INTERNAL_LIMIT, STATUS_OP_MODE_BIT_12,
+    STATUS_OP_MODE_BIT_13, STATUS_TARGET_REACHED, STATUS_WARNING,
+};
+use error::{Error, Result};
+use event::DriveChange;
+use history::{History, Signal};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    mem,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 /// Drive Data
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Drive {
     /// target position
     pub target_position: i32,
     /// Drive real position
     pub real_position: i32,
+    /// Drive demand velocity
+    pub demand_velocity: i32,
+    /// Drive real velocity
+    pub real_velocity: i32,
+    /// Drive demand torque ([0.1%])
+    pub demand_torque: i16,
+    /// Drive real torque ([0.1%])
+    pub real_torque: i16,
+    /// Following error (0x60F4)
+    pub following_error: i32,
+    /// Error code (0x603F)
+    pub error_code: u16,
     /// Drive Digital inputs
     pub digital_inputs: u32,
     /// Control word
     pub control_word: u16,
     /// Status word
     pub status_word: u16,
+    /// Operation mode display (0x6061)
+    pub op_mode: i8,
 }
 
 impl Drive {
@@ -21,15 +55,70 @@ impl Drive {
         Drive {
             target_position: 0,
             real_position: 0,
+            demand_velocity: 0,
+            real_velocity: 0,
+            demand_torque: 0,
+            real_torque: 0,
+            following_error: 0,
+            error_code: 0,
             digital_inputs: 0,
             control_word: 0,
             status_word: 0,
+            op_mode: 0,
         }
     }
+
+    /// CiA 402 狀態 (由 status word 解碼)
+    pub fn cia402_state(&self) -> Cia402State {
+        Cia402State::from_status_word(self.status_word)
+    }
+
+    /// 目前的 operation mode，不支援的 mode 時為 None
+    pub fn operation_mode(&self) -> Option<OperationMode> {
+        OperationMode::try_from(self.op_mode).ok()
+    }
+
+    /// Target reached (status word bit 10)
+    pub fn is_target_reached(&self) -> bool {
+        self.status_word & STATUS_TARGET_REACHED != 0
+    }
+
+    /// Following error (status word bit 13，只在 PP, CSP 模式有效)
+    pub fn has_following_error(&self) -> bool {
+        match self.operation_mode() {
+            Some(OperationMode::ProfilePosition) | Some(OperationMode::CyclicSyncPosition) => {
+                self.status_word & STATUS_OP_MODE_BIT_13 != 0
+            }
+            _ => false,
+        }
+    }
+
+    /// Homing attained (status word bit 12，只在 HM 模式有效)
+    pub fn is_homing_attained(&self) -> bool {
+        self.operation_mode() == Some(OperationMode::Homing)
+            && self.status_word & STATUS_OP_MODE_BIT_12 != 0
+    }
+
+    /// Homing error (status word bit 13，只在 HM 模式有效)
+    pub fn has_homing_error(&self) -> bool {
+        self.operation_mode() == Some(OperationMode::Homing)
+            && self.status_word & STATUS_OP_MODE_BIT_13 != 0
+    }
+
+    /// Warning (status word bit 7)
+    pub fn has_warning(&self) -> bool {
+        self.status_word & STATUS_WARNING != 0
+    }
+
+    /// Internal limit active (status word bit 11)
+    pub fn is_internal_limit_active(&self) -> bool {
+        self.status_word & STATUS_INTERNAL_LIMIT != 0
+    }
 }
 
 /// Slave Data
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Slave {
     /// Vendor ID
     pub vendor_id: u32,
@@ -39,7 +128,7 @@ pub struct Slave {
     pub description: String,
     /// Slave Alias
     pub alias: u16,
-    /// Slave state
+    /// Slave state (AL status)
     pub ec_state: u8,
     /// Drive data
     pub drives: Vec<Drive>,
@@ -66,20 +155,167 @@ impl Slave {
             self.drives.push(Drive::new());
         }
     }
+
+    /// AL state (由 `ec_state` 解碼)
+    pub fn al_state(&self) -> Option<AlState> {
+        AlState::from_al_status(self.ec_state)
+    }
+
+    /// AL status 的 error indication bit
+    pub fn has_al_error(&self) -> bool {
+        self.ec_state & AL_STATUS_ERROR != 0
+    }
+}
+
+/// 啟動自動取得資料時的初始化查詢
+fn push_init_queries(init_queries: &mut Vec<String>) {
+    init_queries.push(".verbose \n".to_string());
+    init_queries.push(".motion \n".to_string());
+    init_queries.push(".ec-links \n".to_string());
+}
+
+/// 從站在 `slaves` 中的 index (即 slave position)
+fn resolve_axis(axes: &HashMap<String, DriveAddr>, target: &DriveTarget) -> Result<DriveAddr> {
+    match *target {
+        DriveTarget::Addr(addr) => Ok(addr),
+        DriveTarget::Axis(ref name) => axes
+            .get(name)
+            .cloned()
+            .ok_or_else(|| Error::AxisNotFound(name.clone())),
+    }
+}
+
+fn find_slave(slaves: &[Slave], addr: &SlaveAddr) -> Option<usize> {
+    match *addr {
+        SlaveAddr::Alias(alias) => slaves
+            .iter()
+            .skip(1)
+            .position(|x| x.alias == alias)
+            .map(|x| x + 1),
+        SlaveAddr::Position(position) if (position as usize) < slaves.len() => {
+            Some(position as usize)
+        }
+        SlaveAddr::Position(_) => None,
+    }
+}
+
+/// EtherCAT 匯流排狀態
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BusStatus {
+    /// 所有從站 AL state 的組合 (bit 0: INIT, bit 1: PRE-OP, bit 2: SAFE-OP, bit 3: OP)
+    /// 有任一從站在該狀態時對應的 bit 為 1
+    pub al_states: u8,
+    /// EtherCAT link 是否連線 (有從站回應且回報了 AL state)
+    pub link_up: bool,
+    /// 有回應的從站數
+    pub slaves_responding: u32,
+    /// 預期的從站數 (參考 `set_expected_slaves`)
+    pub slaves_expected: u32,
+}
+
+impl BusStatus {
+    /// 是否有任一從站在此 AL state (BOOT 無法由 `al_states` 判斷，一律為 false)
+    pub fn has_state(&self, state: AlState) -> bool {
+        state != AlState::Bootstrap && self.al_states & u8::from(state) != 0
+    }
+
+    /// 是否所有從站都在此 AL state
+    pub fn is_all_in(&self, state: AlState) -> bool {
+        state != AlState::Bootstrap && self.al_states == u8::from(state)
+    }
+
+    /// 匯流排是否正常：link 連線、預期的從站都有回應且都在 OP
+    pub fn bus_healthy(&self) -> bool {
+        self.link_up
+            && self.slaves_expected > 0
+            && self.slaves_responding == self.slaves_expected
+            && self.is_all_in(AlState::Op)
+    }
+}
+
+/// Data Pool 的快照
+/// 只在整個訊息套用到 data pool 之後才產生，不會看到更新到一半的資料
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Snapshot {
+    /// 產生快照時 data pool 的 generation，每次 data pool 被訊息更新後加 1
+    pub generation: u64,
+    /// EtherCAT 從站數
+    pub ec_slaves_len: u32,
+    /// EtherCAT 從站狀態
+    pub ec_slaves_state: u32,
+    /// EtherCAT 匯流排狀態
+    pub bus: BusStatus,
+    /// Slaves 的資料
+    pub slaves: Vec<Slave>,
+    /// Axis 名稱對應的驅動器位址
+    pub axes: HashMap<String, DriveAddr>,
+}
+
+impl Snapshot {
+    /// 將 axis 名稱轉換成驅動器位址
+    pub fn resolve(&self, target: &DriveTarget) -> Result<DriveAddr> {
+        resolve_axis(&self.axes, target)
+    }
+
+    /// 從站在 `slaves` 中的 index (即 slave position)
+    pub fn slave_index(&self, addr: &SlaveAddr) -> Option<usize> {
+        find_slave(&self.slaves, addr)
+    }
+
+    /// 取得從站資料
+    pub fn slave(&self, addr: &SlaveAddr) -> Option<&Slave> {
+        self.slave_index(addr).map(|x| &self.slaves[x])
+    }
+
+    /// 取得驅動器資料
+    pub fn drive(&self, addr: &DriveAddr) -> Option<&Drive> {
+        self.slave(&addr.slave)
+            .and_then(|x| x.drives.get(addr.channel as usize))
+    }
+
+    /// 轉成 JSON 字串
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Snapshot::to_json")
+    }
 }
 
 /// Data Pool
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DataPool {
     /// EtherCAT 從站數
     pub ec_slaves_len: u32,
     /// EtherCAT 從站狀態
     pub ec_slaves_state: u32,
+    /// 預期的從站數，None 時為初始化時的從站數
+    ec_slaves_expected: Option<u32>,
     /// slaves 資料初始化旗標
+    #[cfg_attr(feature = "serde", serde(skip))]
     slaves_initing: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
     slaves_inited: bool,
     /// Slaves 的資料
     pub slaves: Vec<Slave>,
+    /// 每次 data pool 被訊息更新後加 1
+    generation: u64,
+    /// Axis 名稱對應的驅動器位址
+    pub axes: HashMap<String, DriveAddr>,
+    /// 需要記錄歷史資料的訊號與深度
+    #[cfg_attr(feature = "serde", serde(skip))]
+    history_depths: HashMap<Signal, usize>,
+    /// 驅動器訊號的歷史資料 (slave position, channel, signal)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    histories: HashMap<(usize, usize, Signal), History>,
+    /// 已經收到第一筆資料的驅動器訊號 (slave position, channel, signal)
+    #[cfg_attr(feature = "serde", serde(skip))]
+    sampled: HashSet<(usize, usize, Signal)>,
+    /// 還沒送出的 DriveChange 事件
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) changes: Vec<DriveChange>,
 
+    #[cfg_attr(feature = "serde", serde(skip))]
     enabled: bool,
 }
 
@@ -89,33 +325,170 @@ impl DataPool {
         DataPool {
             ec_slaves_len: 0,
             ec_slaves_state: 0,
+            ec_slaves_expected: None,
             slaves_initing: false,
             slaves_inited: false,
             slaves: Vec::new(),
+            generation: 0,
+            axes: HashMap::new(),
+            history_depths: HashMap::new(),
+            histories: HashMap::new(),
+            sampled: HashSet::new(),
+            changes: Vec::new(),
 
             enabled: false,
         }
     }
+
+    /// 從站在 `slaves` 中的 index (即 slave position)
+    pub fn slave_index(&self, addr: &SlaveAddr) -> Option<usize> {
+        find_slave(&self.slaves, addr)
+    }
+
+    /// 每次 data pool 被訊息更新後加 1
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// 產生目前資料的快照
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            generation: self.generation,
+            ec_slaves_len: self.ec_slaves_len,
+            ec_slaves_state: self.ec_slaves_state,
+            bus: self.bus_status(),
+            slaves: self.slaves.clone(),
+            axes: self.axes.clone(),
+        }
+    }
+
+    /// EtherCAT 匯流排狀態
+    pub fn bus_status(&self) -> BusStatus {
+        BusStatus {
+            al_states: (self.ec_slaves_state & 0x0F) as u8,
+            link_up: self.ec_slaves_len > 0 && self.ec_slaves_state & 0x0F != 0,
+            slaves_responding: self.ec_slaves_len,
+            slaves_expected: self
+                .ec_slaves_expected
+                .unwrap_or_else(|| self.slaves.len().saturating_sub(1) as u32),
+        }
+    }
+
+    /// 設定預期的從站數，None 時為初始化時的從站數
+    pub fn set_expected_slaves(&mut self, expected: Option<u32>) {
+        self.ec_slaves_expected = expected;
+    }
+
+    /// 轉成 JSON 字串 (不含歷史資料與內部狀態)，可用於匯出或顯示機台狀態
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("DataPool::to_json")
+    }
+
+    /// 取得從站資料
+    pub fn slave(&self, addr: &SlaveAddr) -> Option<&Slave> {
+        self.slave_index(addr).map(|x| &self.slaves[x])
+    }
+
+    /// 取得驅動器資料
+    pub fn drive(&self, addr: &DriveAddr) -> Option<&Drive> {
+        self.slave(&addr.slave)
+            .and_then(|x| x.drives.get(addr.channel as usize))
+    }
+
+    /// 將 axis 名稱轉換成驅動器位址
+    pub fn resolve(&self, target: &DriveTarget) -> Result<DriveAddr> {
+        resolve_axis(&self.axes, target)
+    }
+
+    /// 設定訊號的歷史資料深度，所有驅動器的此訊號都會記錄，0 表示不記錄
+    pub fn set_history_depth(&mut self, signal: Signal, depth: usize) {
+        if depth == 0 {
+            self.history_depths.remove(&signal);
+            self.histories.retain(|k, _| k.2 != signal);
+        } else {
+            self.history_depths.insert(signal, depth);
+            for (_, history) in self.histories.iter_mut().filter(|x| (x.0).2 == signal) {
+                history.set_depth(depth);
+            }
+        }
+    }
+
+    /// 取得驅動器訊號的歷史資料，沒有設定深度或還沒收到資料時為 None
+    pub fn history(&self, addr: &DriveAddr, signal: Signal) -> Option<&History> {
+        let position = self.slave_index(&addr.slave)?;
+        self.histories.get(&(position, addr.channel as usize, signal))
+    }
+
+    /// 驅動器訊號更新，記錄歷史資料，值改變時產生 DriveChange 事件
+    /// 第一筆資料的舊值只是預設值，不產生事件
+    /// 回傳 data pool 是否有改變 (第一筆資料或值改變)
+    fn update_signal(
+        &mut self,
+        position: usize,
+        channel: usize,
+        signal: Signal,
+        old: i64,
+        new: i64,
+    ) -> bool {
+        if let Some(&depth) = self.history_depths.get(&signal) {
+            self.histories
+                .entry((position, channel, signal))
+                .or_insert_with(|| History::new(depth))
+                .push(Instant::now(), new);
+        }
+        let first = self.sampled.insert((position, channel, signal));
+        if !first && old != new {
+            self.changes.push(DriveChange {
+                position: position as u32,
+                channel: channel as u32,
+                signal,
+                old,
+                new,
+            });
+        }
+        first || old != new
+    }
 }
 
-fn ec_slaves_len_process(data_pool: &mut DataPool, _: usize, _: usize, msg: &str) {
+/// 更新欄位，回傳值是否改變
+fn update_field<T: PartialEq>(field: &mut T, x: T) -> bool {
+    let changed = *field != x;
+    *field = x;
+    changed
+}
+
+fn ec_slaves_len_process(data_pool: &mut DataPool, _: usize, _: usize, msg: &str) -> bool {
     if let Ok(x) = msg.parse::<u32>() {
-        data_pool.ec_slaves_len = x;
+        let changed = update_field(&mut data_pool.ec_slaves_len, x);
         if !data_pool.slaves_inited {
             data_pool.slaves_initing = true;
         }
+        changed
+    } else {
+        false
     }
 }
 
-fn ec_slaves_state_process(data_pool: &mut DataPool, _: usize, _: usize, msg: &str) {
+
+fn ec_slaves_state_process(data_pool: &mut DataPool, _: usize, _: usize, msg: &str) -> bool {
     if let Ok(x) = msg.parse::<u32>() {
-        data_pool.ec_slaves_state = x;
+        update_field(&mut data_pool.ec_slaves_state, x)
+    } else {
+        false
     }
 }
 
-fn slave_vendor_id_process(data_pool: &mut DataPool, position: usize, _channel: usize, msg: &str) {
+fn slave_vendor_id_process(
+    data_pool: &mut DataPool,
+    position: usize,
+    _channel: usize,
+    msg: &str,
+) -> bool {
     if let Ok(x) = u32::from_str_radix(msg.trim_start_matches("0x"), 16) {
-        data_pool.slaves[position].vendor_id = x;
+        update_field(&mut data_pool.slaves[position].vendor_id, x)
+    } else {
+        false
     }
 }
 
@@ -124,9 +497,11 @@ fn slave_product_code_process(
     position: usize,
     _channel: usize,
     msg: &str,
-) {
+) -> bool {
     if let Ok(x) = u32::from_str_radix(msg.trim_start_matches("0x"), 16) {
-        data_pool.slaves[position].product_code = x;
+        update_field(&mut data_pool.slaves[position].product_code, x)
+    } else {
+        false
     }
 }
 
@@ -135,19 +510,38 @@ fn slave_description_process(
     position: usize,
     _channel: usize,
     msg: &str,
-) {
-    data_pool.slaves[position].description = msg.to_string();
+) -> bool {
+    if data_pool.slaves[position].description != msg {
+        data_pool.slaves[position].description = msg.to_string();
+        true
+    } else {
+        false
+    }
 }
 
-fn slave_alias_process(data_pool: &mut DataPool, position: usize, _channel: usize, msg: &str) {
+fn slave_alias_process(
+    data_pool: &mut DataPool,
+    position: usize,
+    _channel: usize,
+    msg: &str,
+) -> bool {
     if let Ok(x) = msg.parse::<u16>() {
-        data_pool.slaves[position].alias = x;
+        update_field(&mut data_pool.slaves[position].alias, x)
+    } else {
+        false
     }
 }
 
-fn slave_state_process(data_pool: &mut DataPool, position: usize, _channel: usize, msg: &str) {
+fn slave_state_process(
+    data_pool: &mut DataPool,
+    position: usize,
+    _channel: usize,
+    msg: &str,
+) -> bool {
     if let Ok(x) = u8::from_str_radix(msg.trim_start_matches("0x"), 16) {
-        data_pool.slaves[position].ec_state = x;
+        update_field(&mut data_pool.slaves[position].ec_state, x)
+    } else {
+        false
     }
 }
 
@@ -156,10 +550,13 @@ fn drive_target_position_process(
     position: usize,
     channel: usize,
     msg: &str,
-) {
+) -> bool {
     if let Ok(x) = msg.parse::<i32>() {
         data_pool.slaves[position].reserve_drives(channel);
-        data_pool.slaves[position].drives[channel].target_position = x;
+        let old = mem::replace(&mut data_pool.slaves[position].drives[channel].target_position, x);
+        data_pool.update_signal(position, channel, Signal::TargetPosition, old.into(), x.into())
+    } else {
+        false
     }
 }
 
@@ -168,10 +565,103 @@ fn drive_real_position_process(
     position: usize,
     channel: usize,
     msg: &str,
-) {
+) -> bool {
     if let Ok(x) = msg.parse::<i32>() {
         data_pool.slaves[position].reserve_drives(channel);
-        data_pool.slaves[position].drives[channel].real_position = x;
+        let old = mem::replace(&mut data_pool.slaves[position].drives[channel].real_position, x);
+        data_pool.update_signal(position, channel, Signal::RealPosition, old.into(), x.into())
+    } else {
+        false
+    }
+}
+
+fn drive_demand_velocity_process(
+    data_pool: &mut DataPool,
+    position: usize,
+    channel: usize,
+    msg: &str,
+) -> bool {
+    if let Ok(x) = msg.parse::<i32>() {
+        data_pool.slaves[position].reserve_drives(channel);
+        let old = mem::replace(&mut data_pool.slaves[position].drives[channel].demand_velocity, x);
+        data_pool.update_signal(position, channel, Signal::DemandVelocity, old.into(), x.into())
+    } else {
+        false
+    }
+}
+
+fn drive_real_velocity_process(
+    data_pool: &mut DataPool,
+    position: usize,
+    channel: usize,
+    msg: &str,
+) -> bool {
+    if let Ok(x) = msg.parse::<i32>() {
+        data_pool.slaves[position].reserve_drives(channel);
+        let old = mem::replace(&mut data_pool.slaves[position].drives[channel].real_velocity, x);
+        data_pool.update_signal(position, channel, Signal::RealVelocity, old.into(), x.into())
+    } else {
+        false
+    }
+}
+
+fn drive_demand_torque_process(
+    data_pool: &mut DataPool,
+    position: usize,
+    channel: usize,
+    msg: &str,
+) -> bool {
+    if let Ok(x) = msg.parse::<i16>() {
+        data_pool.slaves[position].reserve_drives(channel);
+        let old = mem::replace(&mut data_pool.slaves[position].drives[channel].demand_torque, x);
+        data_pool.update_signal(position, channel, Signal::DemandTorque, old.into(), x.into())
+    } else {
+        false
+    }
+}
+
+fn drive_real_torque_process(
+    data_pool: &mut DataPool,
+    position: usize,
+    channel: usize,
+    msg: &str,
+) -> bool {
+    if let Ok(x) = msg.parse::<i16>() {
+        data_pool.slaves[position].reserve_drives(channel);
+        let old = mem::replace(&mut data_pool.slaves[position].drives[channel].real_torque, x);
+        data_pool.update_signal(position, channel, Signal::RealTorque, old.into(), x.into())
+    } else {
+        false
+    }
+}
+
+fn drive_following_error_process(
+    data_pool: &mut DataPool,
+    position: usize,
+    channel: usize,
+    msg: &str,
+) -> bool {
+    if let Ok(x) = msg.parse::<i32>() {
+        data_pool.slaves[position].reserve_drives(channel);
+        let old = mem::replace(&mut data_pool.slaves[position].drives[channel].following_error, x);
+        data_pool.update_signal(position, channel, Signal::FollowingError, old.into(), x.into())
+    } else {
+        false
+    }
+}
+
+fn drive_error_code_process(
+    data_pool: &mut DataPool,
+    position: usize,
+    channel: usize,
+    msg: &str,
+) -> bool {
+    if let Ok(x) = u16::from_str_radix(msg.trim_start_matches("0x"), 16) {
+        data_pool.slaves[position].reserve_drives(channel);
+        let old = mem::replace(&mut data_pool.slaves[position].drives[channel].error_code, x);
+        data_pool.update_signal(position, channel, Signal::ErrorCode, old.into(), x.into())
+    } else {
+        false
     }
 }
 
@@ -180,10 +670,13 @@ fn drive_control_word_process(
     position: usize,
     channel: usize,
     msg: &str,
-) {
+) -> bool {
     if let Ok(x) = u16::from_str_radix(msg.trim_start_matches("0x"), 16) {
         data_pool.slaves[position].reserve_drives(channel);
-        data_pool.slaves[position].drives[channel].control_word = x;
+        let old = mem::replace(&mut data_pool.slaves[position].drives[channel].control_word, x);
+        data_pool.update_signal(position, channel, Signal::ControlWord, old.into(), x.into())
+    } else {
+        false
     }
 }
 
@@ -192,10 +685,28 @@ pub fn drive_status_word_process(
     position: usize,
     channel: usize,
     msg: &str,
-) {
+) -> bool {
     if let Ok(x) = u16::from_str_radix(msg.trim_start_matches("0x"), 16) {
         data_pool.slaves[position].reserve_drives(channel);
-        data_pool.slaves[position].drives[channel].status_word = x;
+        let old = mem::replace(&mut data_pool.slaves[position].drives[channel].status_word, x);
+        data_pool.update_signal(position, channel, Signal::StatusWord, old.into(), x.into())
+    } else {
+        false
+    }
+}
+
+fn drive_op_mode_process(
+    data_pool: &mut DataPool,
+    position: usize,
+    channel: usize,
+    msg: &str,
+) -> bool {
+    if let Ok(x) = msg.parse::<i8>() {
+        data_pool.slaves[position].reserve_drives(channel);
+        let old = mem::replace(&mut data_pool.slaves[position].drives[channel].op_mode, x);
+        data_pool.update_signal(position, channel, Signal::OpMode, old.into(), x.into())
+    } else {
+        false
     }
 }
 
@@ -204,10 +715,13 @@ pub fn drive_digital_inputs_process(
     position: usize,
     channel: usize,
     msg: &str,
-) {
+) -> bool {
     if let Ok(x) = u32::from_str_radix(msg.trim_start_matches("0x"), 16) {
         data_pool.slaves[position].reserve_drives(channel);
-        data_pool.slaves[position].drives[channel].digital_inputs = x;
+        let old = mem::replace(&mut data_pool.slaves[position].drives[channel].digital_inputs, x);
+        data_pool.update_signal(position, channel, Signal::DigitalInputs, old.into(), x.into())
+    } else {
+        false
     }
 }
 
@@ -228,11 +742,114 @@ impl Botnana {
             self.internal_handlers.lock().unwrap().clear();
             self.init_queries.lock().unwrap().clear();
             self.cyclic_queries.lock().unwrap().clear();
+            *self.last_query.lock().unwrap() = 0;
 
             data_pool.slaves_inited = false;
             data_pool.slaves_initing = false;
             data_pool.slaves.clear();
+            data_pool.histories.clear();
+            data_pool.sampled.clear();
             data_pool.enabled = false;
+            data_pool.generation += 1;
+            *self.snapshot.lock().unwrap() = Arc::new(data_pool.snapshot());
+        }
+    }
+
+    /// 取得最近一次完整套用訊息後的 data pool 快照
+    /// 快照內的資料一定來自同一次更新，直接鎖住 `data_pool` 讀取則不保證一致
+    /// 可以用 `generation` 判斷資料是否有更新
+    pub fn snapshot(&self) -> Arc<Snapshot> {
+        self.snapshot.lock().unwrap().clone()
+    }
+
+    /// 訊息套用到 data pool 之後，更新 generation 並發布新的快照
+    pub(crate) fn publish_snapshot(&self) {
+        let snapshot = {
+            let mut data_pool = self.data_pool.lock().unwrap();
+            data_pool.generation += 1;
+            Arc::new(data_pool.snapshot())
+        };
+        *self.snapshot.lock().unwrap() = snapshot;
+    }
+
+    /// EtherCAT 匯流排狀態 (需要先啟動自動取得資料的功能)
+    pub fn bus_status(&self) -> BusStatus {
+        self.snapshot().bus
+    }
+
+    /// 匯流排是否正常，可以用於 interlock (參考 `BusStatus::bus_healthy`)
+    pub fn bus_healthy(&self) -> bool {
+        self.bus_status().bus_healthy()
+    }
+
+    /// 設定預期的從站數，None 時為初始化時的從站數
+    pub fn set_expected_slaves(&mut self, expected: Option<u32>) {
+        self.data_pool
+            .lock()
+            .unwrap()
+            .set_expected_slaves(expected);
+        self.publish_snapshot();
+    }
+
+    /// 設定訊號的歷史資料深度，所有驅動器的此訊號都會記錄，0 表示不記錄
+    pub fn set_history_depth(&mut self, signal: Signal, depth: usize) {
+        self.data_pool
+            .lock()
+            .unwrap()
+            .set_history_depth(signal, depth);
+    }
+
+    /// 取得驅動器訊號的歷史資料 (複本)
+    /// @drive    : 驅動器位址或 axis 名稱
+    /// @signal   : 訊號
+    pub fn drive_history<A: Into<DriveTarget>>(&self, drive: A, signal: Signal) -> Option<History> {
+        let data_pool = self.data_pool.lock().unwrap();
+        let addr = data_pool.resolve(&drive.into()).ok()?;
+        data_pool.history(&addr, signal).cloned()
+    }
+
+    /// 由 config.axis.get 取得 axis 設定，並登記 axis 名稱對應的驅動器位址
+    /// 之後的 API 就可以用 axis 名稱選定驅動器
+    /// 不可在 callback 內呼叫，callback 與回應處理在同一個 thread
+    /// @position : axis position
+    /// @timeout  : 等待回應的時間
+    pub fn load_axis_config(&mut self, position: u32, timeout: Duration) -> Result<DriveAddr> {
+        let config = self.config_axis_get(position).wait(timeout)?;
+        let field = |key: &str| config.get(key).and_then(Value::as_u64);
+        let name = config.get("name").and_then(Value::as_str);
+        match (
+            name,
+            field("drive_alias"),
+            field("drive_slave_position"),
+            field("drive_channel"),
+        ) {
+            (Some(name), Some(alias), Some(slave_position), Some(channel)) => {
+                let addr = DriveAddr::new(alias as u16, slave_position as u16, channel as u16);
+                self.data_pool
+                    .lock()
+                    .unwrap()
+                    .axes
+                    .insert(name.to_owned(), addr);
+                self.publish_snapshot();
+                Ok(addr)
+            }
+            _ => Err(Error::Parse(format!("Invalid axis config: {}", config))),
+        }
+    }
+
+    /// 重新連線後重新執行初始化查詢
+    /// 保留 data pool 中的資料、歷史資料與 handlers，
+    /// 匯流排可能已經改變，收到 `slaves_responding` 後依新的從站數重新初始化從站
+    pub(crate) fn restart_auto_query(&mut self) {
+        let mut data_pool = self.data_pool.lock().unwrap();
+        if data_pool.enabled {
+            data_pool.slaves_inited = false;
+            data_pool.slaves_initing = false;
+            let mut init_queries = self.init_queries.lock().unwrap();
+            init_queries.clear();
+            push_init_queries(&mut init_queries);
+            self.cyclic_queries.lock().unwrap().clear();
+            *self.last_query.lock().unwrap() = 0;
         }
     }
 
@@ -243,17 +860,15 @@ impl Botnana {
             Box::new(ec_slaves_len_process),
         );
 
-        let mut init_queries = self.init_queries.lock().unwrap();
-        init_queries.push(".verbose \n".to_string());
-        init_queries.push(".motion \n".to_string());
-        init_queries.push(".ec-links \n".to_string());
+        push_init_queries(&mut self.init_queries.lock().unwrap());
     }
 
     /// Data Pool Forth
-    /// 檢查是否有初始化的需求
-    pub(crate) fn data_pool_forth(&mut self) {
+    /// 檢查是否有初始化的需求，有進行初始化時回傳 true
+    pub(crate) fn data_pool_forth(&mut self) -> bool {
         let mut data_pool = self.data_pool.lock().unwrap();
-        if data_pool.slaves_initing {
+        let initing = data_pool.slaves_initing;
+        if initing {
             data_pool.slaves_initing = false;
             data_pool.slaves_inited = true;
 
@@ -265,13 +880,17 @@ impl Botnana {
             internal_handlers.insert("al_states".to_owned(), Box::new(ec_slaves_state_process));
 
             // slaves 多擴充一個，使 index 從 1 開始
-            data_pool.slaves.push(Slave::new());
+            // 重新初始化時保留仍然存在的從站資料，完整資料會由 `.slave` 重新取得
+            let slaves_len = data_pool.ec_slaves_len as usize + 1;
+            data_pool.slaves.truncate(slaves_len);
+            while data_pool.slaves.len() < slaves_len {
+                data_pool.slaves.push(Slave::new());
+            }
 
             for i in 1..data_pool.ec_slaves_len + 1 {
                 init_queries.push(format!("{} .slave\n", i));
                 cyclic_queries.push(format!("{} .slave-diff\n", i));
 
-                data_pool.slaves.push(Slave::new());
                 internal_handlers.insert("vendor".to_owned(), Box::new(slave_vendor_id_process));
 
                 internal_handlers
@@ -294,6 +913,30 @@ impl Botnana {
                     "real_position".to_owned(),
                     Box::new(drive_real_position_process),
                 );
+                internal_handlers.insert(
+                    "demand_velocity".to_owned(),
+                    Box::new(drive_demand_velocity_process),
+                );
+                internal_handlers.insert(
+                    "real_velocity".to_owned(),
+                    Box::new(drive_real_velocity_process),
+                );
+                internal_handlers.insert(
+                    "demand_torque".to_owned(),
+                    Box::new(drive_demand_torque_process),
+                );
+                internal_handlers.insert(
+                    "real_torque".to_owned(),
+                    Box::new(drive_real_torque_process),
+                );
+                internal_handlers.insert(
+                    "following_error".to_owned(),
+                    Box::new(drive_following_error_process),
+                );
+                internal_handlers.insert(
+                    "error_code".to_owned(),
+                    Box::new(drive_error_code_process),
+                );
                 internal_handlers.insert(
                     "control_word".to_owned(),
                     Box::new(drive_control_word_process),
@@ -306,7 +949,199 @@ impl Botnana {
                     "digital_inputs".to_owned(),
                     Box::new(drive_digital_inputs_process),
                 );
+                internal_handlers.insert("op_mode".to_owned(), Box::new(drive_op_mode_process));
             }
         }
+        initing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use protocol;
+
+    /// 以內部 handlers 處理訊息，回傳資料是否有改變
+    fn feed(botnana: &Botnana, message: &str) -> bool {
+        let internal_handlers = botnana.internal_handlers.lock().unwrap();
+        let mut data_pool = botnana.data_pool.lock().unwrap();
+        let mut updated = false;
+        for record in protocol::parse(message) {
+            if let Some(handler) = internal_handlers.get(record.name) {
+                updated |= handler(
+                    &mut data_pool,
+                    record.position(),
+                    record.channel(),
+                    record.value,
+                );
+            }
+        }
+        updated
+    }
+
+    /// 已初始化一個從站的 Botnana
+    fn botnana_with_slave() -> Botnana {
+        let mut botnana = Botnana::new();
+        botnana.config_init_queries_and_hadlers();
+        assert!(feed(&botnana, "slaves_responding|1"));
+        assert!(botnana.data_pool_forth());
+        botnana
+    }
+
+    #[test]
+    fn drive_tag_handlers() {
+        let botnana = botnana_with_slave();
+        assert!(feed(
+            &botnana,
+            "demand_velocity.1.1|1000|real_velocity.1.1|-998|demand_torque.1.1|300\
+             |real_torque.1.1|-25|following_error.1.1|-7|error_code.1.1|0x7500|op_mode.1.1|6",
+        ));
+        {
+            let data_pool = botnana.data_pool.lock().unwrap();
+            let drive = &data_pool.slaves[1].drives[1];
+            assert_eq!(drive.demand_velocity, 1000);
+            assert_eq!(drive.real_velocity, -998);
+            assert_eq!(drive.demand_torque, 300);
+            assert_eq!(drive.real_torque, -25);
+            assert_eq!(drive.following_error, -7);
+            assert_eq!(drive.error_code, 0x7500);
+            assert_eq!(drive.operation_mode(), Some(OperationMode::Homing));
+        }
+        // 相同的資料不算改變
+        assert!(!feed(&botnana, "real_velocity.1.1|-998|op_mode.1.1|6"));
+        // error code 沒有 0x 時也是 hex
+        assert!(feed(&botnana, "error_code.1.1|FF01"));
+        assert_eq!(botnana.data_pool.lock().unwrap().slaves[1].drives[1].error_code, 0xFF01);
+    }
+
+    #[test]
+    fn restart_requeries_slaves() {
+        let mut botnana = botnana_with_slave();
+        botnana.data_pool.lock().unwrap().enabled = true;
+        assert!(feed(&botnana, "vendor.1|0x66F"));
+
+        // 重新連線後匯流排多了兩個從站
+        botnana.restart_auto_query();
+        assert!(botnana.cyclic_queries.lock().unwrap().is_empty());
+        assert!(!botnana.data_pool_forth());
+        assert!(feed(&botnana, "slaves_responding|3"));
+        assert!(botnana.data_pool_forth());
+        {
+            let data_pool = botnana.data_pool.lock().unwrap();
+            assert_eq!(data_pool.slaves.len(), 4);
+            assert_eq!(data_pool.slaves[1].vendor_id, 0x66F);
+        }
+        assert!(botnana
+            .init_queries
+            .lock()
+            .unwrap()
+            .contains(&"3 .slave\n".to_owned()));
+        assert!(feed(&botnana, "vendor.3|0x2"));
+
+        // 相同的從站數也會重新初始化
+        botnana.restart_auto_query();
+        assert!(!feed(&botnana, "slaves_responding|3"));
+        assert!(botnana.data_pool_forth());
+        assert_eq!(
+            botnana.cyclic_queries.lock().unwrap().len(),
+            4,
+            "cyclic queries should not be duplicated"
+        );
+
+        // 從站變少
+        botnana.restart_auto_query();
+        assert!(feed(&botnana, "slaves_responding|1"));
+        assert!(botnana.data_pool_forth());
+        assert_eq!(botnana.data_pool.lock().unwrap().slaves.len(), 2);
+    }
+
+    #[test]
+    fn drive_tag_handlers_bad_input() {
+        let botnana = botnana_with_slave();
+        assert!(feed(&botnana, "real_velocity.1.1|5|demand_torque.1.1|10|error_code.1.1|0x10"));
+        assert!(!feed(
+            &botnana,
+            "real_velocity.1.1|fast|demand_torque.1.1|40000|error_code.1.1|0xZZ\
+             |error_code.1.1|0x10000|op_mode.1.1|200|following_error.1.1|",
+        ));
+        let data_pool = botnana.data_pool.lock().unwrap();
+        let drive = &data_pool.slaves[1].drives[1];
+        assert_eq!(drive.real_velocity, 5);
+        assert_eq!(drive.demand_torque, 10);
+        assert_eq!(drive.error_code, 0x10);
+        assert_eq!(drive.op_mode, 0);
+    }
+
+    #[test]
+    fn op_mode_bits_follow_operation_mode() {
+        let mut drive = Drive::new();
+        drive.status_word = STATUS_OP_MODE_BIT_12 | STATUS_OP_MODE_BIT_13;
+        drive.op_mode = i8::from(OperationMode::ProfilePosition);
+        assert!(drive.has_following_error());
+        assert!(!drive.has_homing_error());
+        assert!(!drive.is_homing_attained());
+        drive.op_mode = i8::from(OperationMode::Homing);
+        assert!(!drive.has_following_error());
+        assert!(drive.has_homing_error());
+        assert!(drive.is_homing_attained());
+        drive.op_mode = i8::from(OperationMode::ProfileVelocity);
+        assert!(!drive.has_following_error());
+        assert!(!drive.has_homing_error());
+    }
+
+    #[test]
+    fn bus_status_from_slaves() {
+        let mut data_pool = DataPool::new();
+        assert!(!data_pool.bus_status().link_up);
+        data_pool.ec_slaves_len = 2;
+        data_pool.ec_slaves_state = 0x0C;
+        let bus = data_pool.bus_status();
+        assert!(bus.link_up);
+        assert_eq!(bus.slaves_responding, 2);
+        assert!(bus.has_state(AlState::SafeOp));
+        assert!(bus.has_state(AlState::Op));
+        assert!(!bus.has_state(AlState::Init));
+        assert!(!bus.is_all_in(AlState::Op));
+        // 沒有從站回應時 link 中斷
+        data_pool.ec_slaves_len = 0;
+        assert!(!data_pool.bus_status().link_up);
+    }
+
+    #[test]
+    fn bus_healthy() {
+        let bus = BusStatus {
+            al_states: 0x08,
+            link_up: true,
+            slaves_responding: 2,
+            slaves_expected: 2,
+        };
+        assert!(bus.bus_healthy());
+        assert!(!BusStatus { link_up: false, ..bus }.bus_healthy());
+        assert!(!BusStatus { slaves_responding: 1, ..bus }.bus_healthy());
+        assert!(!BusStatus { slaves_expected: 0, slaves_responding: 0, ..bus }.bus_healthy());
+        assert!(!BusStatus { al_states: 0x0C, ..bus }.bus_healthy());
+
+        let mut data_pool = DataPool::new();
+        data_pool.ec_slaves_len = 2;
+        data_pool.ec_slaves_state = 0x08;
+        data_pool.set_expected_slaves(Some(2));
+        assert!(data_pool.bus_status().bus_healthy());
+        data_pool.set_expected_slaves(Some(3));
+        assert!(!data_pool.bus_status().bus_healthy());
+    }
+
+    #[test]
+    fn first_sample_emits_no_change() {
+        let mut data_pool = DataPool::new();
+        data_pool.update_signal(1, 1, Signal::RealPosition, 0, 100);
+        assert!(data_pool.changes.is_empty());
+        data_pool.update_signal(1, 1, Signal::RealPosition, 100, 100);
+        assert!(data_pool.changes.is_empty());
+        data_pool.update_signal(1, 1, Signal::RealPosition, 100, 120);
+        assert_eq!(data_pool.changes.len(), 1);
+        assert_eq!((data_pool.changes[0].old, data_pool.changes[0].new), (100, 120));
+        // 其他驅動器的第一筆資料
+        data_pool.update_signal(1, 2, Signal::RealPosition, 0, 5);
+        assert_eq!(data_pool.changes.len(), 1);
     }
 }
@@ -1,122 +1,274 @@
+use address::{DriveAddr, DriveTarget};
 use botnana::Botnana;
+use cia402::{Cia402State, OperationMode};
+use error::{Error, Result};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// 等待驅動器狀態時，檢查 data pool 的間隔
+const DRIVE_POLL_INTERVAL_MS: u64 = 10;
+/// 還沒進入 Operation Enabled 時，重送 drive-on 的間隔
+const DRIVE_ON_RESEND_INTERVAL_MS: u64 = 100;
+
+/// `drive_on_and_wait` 每次檢查狀態後的動作
+#[derive(Debug, PartialEq)]
+enum DriveOnStep {
+    /// 已進入 Operation Enabled
+    Done,
+    /// 送出此 Forth word (`reset-fault` 或 `drive-on`)
+    Send(&'static str),
+    /// 等待驅動器自行轉換狀態
+    Wait,
+}
+
+/// 依驅動器狀態決定 `drive_on_and_wait` 要送出的命令
+/// 狀態改變或每隔一段時間仍未到達時送出 drive-on，Fault 時最多清除一次異警
+struct DriveOnSequence {
+    reset_fault: bool,
+    fault_reset_sent: bool,
+    last_state: Option<Cia402State>,
+    last_sent: Option<Instant>,
+}
+
+impl DriveOnSequence {
+    fn new(reset_fault: bool) -> DriveOnSequence {
+        DriveOnSequence {
+            reset_fault,
+            fault_reset_sent: false,
+            last_state: None,
+            last_sent: None,
+        }
+    }
+
+    /// 依目前的狀態決定動作，不能清除的 Fault 回傳 `Error::DriveState`
+    /// 還沒收到驅動器資料 (None) 時等待
+    fn step(&mut self, state: Option<Cia402State>, now: Instant) -> Result<DriveOnStep> {
+        if state == Some(Cia402State::OperationEnabled) {
+            return Ok(DriveOnStep::Done);
+        }
+        let changed = state != self.last_state;
+        let interval = Duration::from_millis(DRIVE_ON_RESEND_INTERVAL_MS);
+        let resend = changed
+            || match self.last_sent {
+                Some(x) => now.duration_since(x) >= interval,
+                None => true,
+            };
+        let step = match state {
+            // 清除異警後等待狀態改變，不重複送出 reset-fault
+            Some(Cia402State::Fault) if changed => {
+                if !self.reset_fault || self.fault_reset_sent {
+                    return Err(Error::DriveState(Cia402State::Fault));
+                }
+                self.fault_reset_sent = true;
+                DriveOnStep::Send("reset-fault")
+            }
+            Some(Cia402State::SwitchOnDisabled)
+            | Some(Cia402State::ReadyToSwitchOn)
+            | Some(Cia402State::SwitchedOn)
+            | Some(Cia402State::QuickStopActive)
+                if resend =>
+            {
+                self.last_sent = Some(now);
+                DriveOnStep::Send("drive-on")
+            }
+            _ => DriveOnStep::Wait,
+        };
+        if state.is_some() {
+            self.last_state = state;
+        }
+        Ok(step)
+    }
+}
+
+/// PP 模式下運動到目標位置的 script
+pub(crate) fn target_position_script(addr: &DriveAddr, relative: bool, target: i64) -> String {
+    let rel_cmd = if relative { "+pp-rel" } else { "-pp-rel" };
+    format!(
+        "{target} {channel} {slave} target-p! {channel} {slave} {rel_cmd} {channel} {slave} go",
+        slave = addr.slave,
+        channel = addr.channel,
+        rel_cmd = rel_cmd,
+        target = target,
+    )
+}
 
 impl Botnana {
+    /// 將 axis 名稱轉換成驅動器位址
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn resolve_drive<A: Into<DriveTarget>>(&self, drive: A) -> Result<DriveAddr> {
+        self.snapshot().resolve(&drive.into())
+    }
+
     /// Set drive operation mode
-    /// @alias    : slave alias
-    /// @position : slave position
-    /// @channel  : channel
-    /// @mode	  : operation mode (PP, PV, HM, TQ, CSP, CSV, CST)
-    fn set_drive_mode(&mut self, alias: u16, position: u16, channel: u16, mode: u8) {
-        self.send_script_to_buffer(&format!(
+    /// 切換是否完成可以由 `drive_operation_mode` 確認
+    /// @drive    : 驅動器位址或 axis 名稱
+    /// @mode     : operation mode
+    pub fn try_set_drive_mode<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        mode: OperationMode,
+    ) -> Result<()> {
+        let addr = self.resolve_drive(drive)?;
+        self.try_send_script_to_buffer(&format!(
             "{} {} {} op-mode!",
-            mode,
-            channel,
-            slave_position!(alias, position)
-        ));
+            i8::from(mode),
+            addr.channel,
+            addr.slave
+        ))
     }
 
     /// Set drive operation mode to PP (位置模式)
     /// @alias    : slave alias
     /// @position : slave position
     /// @channel  : channel
+    #[deprecated(note = "use try_set_drive_mode with OperationMode::ProfilePosition")]
     pub fn set_drive_mode_to_pp(&mut self, alias: u16, position: u16, channel: u16) {
-        self.set_drive_mode(alias, position, channel, 1);
+        let _ = self.try_set_drive_mode(
+            DriveAddr::new(alias, position, channel),
+            OperationMode::ProfilePosition,
+        );
     }
 
     /// Set drive operation mode to PV (速度模式, SDO)
     /// @alias    : slave alias
     /// @position : slave position
     /// @channel  : channel
+    #[deprecated(note = "use try_set_drive_mode with OperationMode::ProfileVelocity")]
     pub fn set_drive_mode_to_pv(&mut self, alias: u16, position: u16, channel: u16) {
-        self.set_drive_mode(alias, position, channel, 3);
+        let _ = self.try_set_drive_mode(
+            DriveAddr::new(alias, position, channel),
+            OperationMode::ProfileVelocity,
+        );
     }
 
     /// Set drive operation mode to HM (回歸原點, SDO)
     /// @alias    : slave alias
     /// @position : slave position
     /// @channel  : channel
+    #[deprecated(note = "use try_set_drive_mode with OperationMode::Homing")]
     pub fn set_drive_mode_to_hm(&mut self, alias: u16, position: u16, channel: u16) {
-        self.set_drive_mode(alias, position, channel, 6);
+        let _ = self.try_set_drive_mode(
+            DriveAddr::new(alias, position, channel),
+            OperationMode::Homing,
+        );
     }
 
     /// Set drive operation mode to TQ (扭力模式, SDO)
     /// @alias    : slave alias
     /// @position : slave position
     /// @channel  : channel
+    #[deprecated(note = "use try_set_drive_mode with OperationMode::ProfileTorque")]
     pub fn set_drive_mode_to_tq(&mut self, alias: u16, position: u16, channel: u16) {
-        self.set_drive_mode(alias, position, channel, 4);
+        let _ = self.try_set_drive_mode(
+            DriveAddr::new(alias, position, channel),
+            OperationMode::ProfileTorque,
+        );
     }
 
     /// Set drive operation mode to CSP (時間同步位置模式, SDO)
     /// @alias    : slave alias
     /// @position : slave position
     /// @channel  : channel
+    #[deprecated(note = "use try_set_drive_mode with OperationMode::CyclicSyncPosition")]
     pub fn set_drive_mode_to_csp(&mut self, alias: u16, position: u16, channel: u16) {
-        self.set_drive_mode(alias, position, channel, 8);
+        let _ = self.try_set_drive_mode(
+            DriveAddr::new(alias, position, channel),
+            OperationMode::CyclicSyncPosition,
+        );
     }
 
     /// Set drive operation mode to CSV (時間同步速度模式, SDO)
     /// @alias    : slave alias
     /// @position : slave position
     /// @channel  : channel
+    #[deprecated(note = "use try_set_drive_mode with OperationMode::CyclicSyncVelocity")]
     pub fn set_drive_mode_to_csv(&mut self, alias: u16, position: u16, channel: u16) {
-        self.set_drive_mode(alias, position, channel, 9);
+        let _ = self.try_set_drive_mode(
+            DriveAddr::new(alias, position, channel),
+            OperationMode::CyclicSyncVelocity,
+        );
     }
 
     /// Set drive operation mode to CST (時間同步扭力模式, SDO)
     /// @alias    : slave alias
     /// @position : slave position
     /// @channel  : channel
+    #[deprecated(note = "use try_set_drive_mode with OperationMode::CyclicSyncTorque")]
     pub fn set_drive_mode_to_cst(&mut self, alias: u16, position: u16, channel: u16) {
-        self.set_drive_mode(alias, position, channel, 10);
+        let _ = self.try_set_drive_mode(
+            DriveAddr::new(alias, position, channel),
+            OperationMode::CyclicSyncTorque,
+        );
     }
 
     /// Reset drive fault (清除驅動器異警)
     /// @alias    : slave alias
     /// @position : slave position
     /// @channel  : channel
+    #[deprecated(note = "use try_reset_drive_fault")]
     pub fn reset_drive_fault(&mut self, alias: u16, position: u16, channel: u16) {
-        self.send_script_to_buffer(&format!(
-            "{} {} reset-fault",
-            channel,
-            slave_position!(alias, position)
-        ));
+        let _ = self.try_reset_drive_fault(DriveAddr::new(alias, position, channel));
+    }
+
+    /// Reset drive fault (清除驅動器異警)
+    /// @drive    : 驅動器位址或 axis 名稱
+    /// 無法解析驅動器或送出失敗時回傳錯誤
+    pub fn try_reset_drive_fault<A: Into<DriveTarget>>(&mut self, drive: A) -> Result<()> {
+        let addr = self.resolve_drive(drive)?;
+        self.try_send_script_to_buffer(&format!("{} {} reset-fault", addr.channel, addr.slave))
     }
 
     /// Drive On
     /// @alias    : slave alias
     /// @position : slave position
     /// @channel  : channel
+    #[deprecated(note = "use try_drive_on")]
     pub fn drive_on(&mut self, alias: u16, position: u16, channel: u16) {
-        self.send_script_to_buffer(&format!(
-            "{} {} drive-on",
-            channel,
-            slave_position!(alias, position)
-        ));
+        let _ = self.try_drive_on(DriveAddr::new(alias, position, channel));
+    }
+
+    /// Drive On
+    /// @drive    : 驅動器位址或 axis 名稱
+    /// 無法解析驅動器或送出失敗時回傳錯誤
+    pub fn try_drive_on<A: Into<DriveTarget>>(&mut self, drive: A) -> Result<()> {
+        let addr = self.resolve_drive(drive)?;
+        self.try_send_script_to_buffer(&format!("{} {} drive-on", addr.channel, addr.slave))
     }
 
     /// Drive Off
     /// @alias    : slave alias
     /// @position : slave position
     /// @channel  : channel
+    #[deprecated(note = "use try_drive_off")]
     pub fn drive_off(&mut self, alias: u16, position: u16, channel: u16) {
-        self.send_script_to_buffer(&format!(
-            "{} {} drive-off",
-            channel,
-            slave_position!(alias, position)
-        ));
+        let _ = self.try_drive_off(DriveAddr::new(alias, position, channel));
+    }
+
+    /// Drive Off
+    /// @drive    : 驅動器位址或 axis 名稱
+    /// 無法解析驅動器或送出失敗時回傳錯誤
+    pub fn try_drive_off<A: Into<DriveTarget>>(&mut self, drive: A) -> Result<()> {
+        let addr = self.resolve_drive(drive)?;
+        self.try_send_script_to_buffer(&format!("{} {} drive-off", addr.channel, addr.slave))
     }
 
     /// Drive Stop
     /// @alias    : slave alias
     /// @position : slave position
     /// @channel  : channel
+    #[deprecated(note = "use try_drive_stop")]
     pub fn drive_stop(&mut self, alias: u16, position: u16, channel: u16) {
-        self.send_script_to_buffer(&format!(
-            "{} {} drive-stop",
-            channel,
-            slave_position!(alias, position)
-        ));
+        let _ = self.try_drive_stop(DriveAddr::new(alias, position, channel));
+    }
+
+    /// Drive Stop
+    /// @drive    : 驅動器位址或 axis 名稱
+    /// 無法解析驅動器或送出失敗時回傳錯誤
+    pub fn try_drive_stop<A: Into<DriveTarget>>(&mut self, drive: A) -> Result<()> {
+        let addr = self.resolve_drive(drive)?;
+        self.try_send_script_to_buffer(&format!("{} {} drive-stop", addr.channel, addr.slave))
     }
 
     /// Drive halt
@@ -124,14 +276,19 @@ impl Botnana {
     /// @position : slave position
     /// @channel  : channel
     /// @halt     : halt or not halt
+    #[deprecated(note = "use try_drive_halt")]
     pub fn drive_halt(&mut self, alias: u16, position: u16, channel: u16, halt: bool) {
+        let _ = self.try_drive_halt(DriveAddr::new(alias, position, channel), halt);
+    }
+
+    /// Drive halt
+    /// @drive    : 驅動器位址或 axis 名稱
+    /// @halt     : halt or not halt
+    /// 無法解析驅動器或送出失敗時回傳錯誤
+    pub fn try_drive_halt<A: Into<DriveTarget>>(&mut self, drive: A, halt: bool) -> Result<()> {
+        let addr = self.resolve_drive(drive)?;
         let cmd = if halt { "+drive-halt" } else { "-drive-halt" };
-        self.send_script_to_buffer(&format!(
-            "{} {} {}",
-            channel,
-            slave_position!(alias, position),
-            cmd
-        ));
+        self.try_send_script_to_buffer(&format!("{} {} {}", addr.channel, addr.slave, cmd))
     }
 
     /// Set drive profile vecloity (For PP Mode, SDO)
@@ -139,6 +296,7 @@ impl Botnana {
     /// @position : slave position
     /// @channel  : channel
     /// @velocity : velocity （([pulse/s]，但會因驅動器而異)
+    #[deprecated(note = "use try_set_drive_profile_velocity")]
     pub fn set_drive_profile_velocity(
         &mut self,
         alias: u16,
@@ -146,19 +304,34 @@ impl Botnana {
         channel: u16,
         velocity: u32,
     ) {
-        self.send_script_to_buffer(&format!(
-            "{} {} {} profile-v!",
+        let _ = self.try_set_drive_profile_velocity(
+            DriveAddr::new(alias, position, channel),
             velocity,
-            channel,
-            slave_position!(alias, position)
-        ));
+        );
+    }
+
+    /// Set drive profile vecloity (For PP Mode, SDO)
+    /// @drive    : 驅動器位址或 axis 名稱
+    /// @velocity : velocity （([pulse/s]，但會因驅動器而異)
+    /// 無法解析驅動器或送出失敗時回傳錯誤
+    pub fn try_set_drive_profile_velocity<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        velocity: u32,
+    ) -> Result<()> {
+        let addr = self.resolve_drive(drive)?;
+        self.try_send_script_to_buffer(&format!(
+            "{} {} {} profile-v!",
+            velocity, addr.channel, addr.slave
+        ))
     }
 
     /// Set drive profile acceleration (For PP, PV Mode, SDO)
-    /// @alias        : slave alias
-    /// @position     : slave position
-    /// @channel      : channel
+    /// @alias    : slave alias
+    /// @position : slave position
+    /// @channel  : channel
     /// @acceleration : acceleration （([pulse/s^2]，但會因驅動器而異)
+    #[deprecated(note = "use try_set_drive_profile_acceleration")]
     pub fn set_drive_profile_acceleration(
         &mut self,
         alias: u16,
@@ -166,19 +339,34 @@ impl Botnana {
         channel: u16,
         acceleration: u32,
     ) {
-        self.send_script_to_buffer(&format!(
-            "{} {} {} profile-a1!",
+        let _ = self.try_set_drive_profile_acceleration(
+            DriveAddr::new(alias, position, channel),
             acceleration,
-            channel,
-            slave_position!(alias, position)
-        ));
+        );
+    }
+
+    /// Set drive profile acceleration (For PP, PV Mode, SDO)
+    /// @drive        : 驅動器位址或 axis 名稱
+    /// @acceleration : acceleration （([pulse/s^2]，但會因驅動器而異)
+    /// 無法解析驅動器或送出失敗時回傳錯誤
+    pub fn try_set_drive_profile_acceleration<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        acceleration: u32,
+    ) -> Result<()> {
+        let addr = self.resolve_drive(drive)?;
+        self.try_send_script_to_buffer(&format!(
+            "{} {} {} profile-a1!",
+            acceleration, addr.channel, addr.slave
+        ))
     }
 
     /// Set drive profile deceleration (For PP, PV Mode, SDO)
-    /// @alias        : slave alias
-    /// @position     : slave position
-    /// @channel      : channel
+    /// @alias    : slave alias
+    /// @position : slave position
+    /// @channel  : channel
     /// @deceleration : deceleration ([pulse/s^2]，但會因驅動器而異)
+    #[deprecated(note = "use try_set_drive_profile_deceleration")]
     pub fn set_drive_profile_deceleration(
         &mut self,
         alias: u16,
@@ -186,20 +374,35 @@ impl Botnana {
         channel: u16,
         deceleration: u32,
     ) {
-        self.send_script_to_buffer(&format!(
-            "{} {} {} profile-a2!",
+        let _ = self.try_set_drive_profile_deceleration(
+            DriveAddr::new(alias, position, channel),
             deceleration,
-            channel,
-            slave_position!(alias, position)
-        ));
+        );
+    }
+
+    /// Set drive profile deceleration (For PP, PV Mode, SDO)
+    /// @drive        : 驅動器位址或 axis 名稱
+    /// @deceleration : deceleration ([pulse/s^2]，但會因驅動器而異)
+    /// 無法解析驅動器或送出失敗時回傳錯誤
+    pub fn try_set_drive_profile_deceleration<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        deceleration: u32,
+    ) -> Result<()> {
+        let addr = self.resolve_drive(drive)?;
+        self.try_send_script_to_buffer(&format!(
+            "{} {} {} profile-a2!",
+            deceleration, addr.channel, addr.slave
+        ))
     }
 
     /// PP 模式下進行運動
     /// @alias    : slave alias
-    /// @position : slave position  
+    /// @position : slave position
     /// @channel  : channel
     /// @relative : 相對位置或是絕對位置，有些驅動器不支援相對位置
     /// @target   : 目標位置 [pulse]
+    #[deprecated(note = "use try_drive_move_to_target_position")]
     pub fn drive_move_to_target_position(
         &mut self,
         alias: u16,
@@ -208,14 +411,30 @@ impl Botnana {
         relative: bool,
         target: u32,
     ) {
-        let rel_cmd = if relative { "+pp-rel" } else { "-pp-rel" };
-        self.send_script_to_buffer(&format!(
-            "{target} {channel} {slave} target-p! {channel} {slave} {rel_cmd} {channel} {slave} go",
-            slave = slave_position!(alias, position),
-            channel = channel,
-            rel_cmd = rel_cmd,
-            target = target,
-        ));
+        let _ = self.try_drive_move_to_target_position(
+            DriveAddr::new(alias, position, channel),
+            relative,
+            target,
+        );
+    }
+
+    /// PP 模式下進行運動
+    /// @drive    : 驅動器位址或 axis 名稱
+    /// @relative : 相對位置或是絕對位置，有些驅動器不支援相對位置
+    /// @target   : 目標位置 [pulse]
+    /// 無法解析驅動器或送出失敗時回傳錯誤
+    pub fn try_drive_move_to_target_position<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        relative: bool,
+        target: u32,
+    ) -> Result<()> {
+        let addr = self.resolve_drive(drive)?;
+        self.try_send_script_to_buffer(&target_position_script(
+            &addr,
+            relative,
+            i64::from(target),
+        ))
     }
 
     /// Set homing profile (SDO)
@@ -226,6 +445,8 @@ impl Botnana {
     /// @speed1   : 搜尋 switch (正負極限或是原點開開) 的速度 ([pulse/s]，但會因驅動器而異)
     /// @speed2   : 搜尋 index pulse 的速度 ([pulse/s]，但會因驅動器而異)
     /// @acceleration  : 加速度 ([pulse/s^2]，但會因驅動器而異)
+    #[deprecated(note = "use try_set_drive_homing_profile")]
+    #[allow(clippy::too_many_arguments)]
     pub fn set_drive_homing_profile(
         &mut self,
         alias: u16,
@@ -236,27 +457,57 @@ impl Botnana {
         speed2: u32,
         acceleration: u32,
     ) {
-        self.send_script_to_buffer(&format!(
+        let _ = self.try_set_drive_homing_profile(
+            DriveAddr::new(alias, position, channel),
+            method,
+            speed1,
+            speed2,
+            acceleration,
+        );
+    }
+
+    /// Set homing profile (SDO)
+    /// @drive    : 驅動器位址或 axis 名稱
+    /// @method   : 回歸原點的方法 （支援的方法會因驅動器而異）
+    /// @speed1   : 搜尋 switch (正負極限或是原點開開) 的速度 ([pulse/s]，但會因驅動器而異)
+    /// @speed2   : 搜尋 index pulse 的速度 ([pulse/s]，但會因驅動器而異)
+    /// @acceleration  : 加速度 ([pulse/s^2]，但會因驅動器而異)
+    /// 無法解析驅動器或送出失敗時回傳錯誤
+    pub fn try_set_drive_homing_profile<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        method: i8,
+        speed1: u32,
+        speed2: u32,
+        acceleration: u32,
+    ) -> Result<()> {
+        let addr = self.resolve_drive(drive)?;
+        self.try_send_script_to_buffer(&format!(
             "{method} {channel} {slave} homing-method! {speed1} {channel} {slave} homing-v1! {speed2} {channel} {slave} homing-v2! {acceleration} {channel} {slave} homing-a!",
-            slave = slave_position!(alias, position),
-            channel = channel,
+            slave = addr.slave,
+            channel = addr.channel,
             method = method,
             speed1 = speed1,
             speed2 = speed2,
             acceleration = acceleration,
-        ));
+        ))
     }
 
     /// HM 模式開始進行回歸原點
     /// @alias    : slave alias
     /// @position : slave position
     /// @channel  : channel
+    #[deprecated(note = "use try_drive_homing_start")]
     pub fn drive_homing_start(&mut self, alias: u16, position: u16, channel: u16) {
-        self.send_script_to_buffer(&format!(
-            "{} {} go",
-            channel,
-            slave_position!(alias, position),
-        ));
+        let _ = self.try_drive_homing_start(DriveAddr::new(alias, position, channel));
+    }
+
+    /// HM 模式開始進行回歸原點
+    /// @drive    : 驅動器位址或 axis 名稱
+    /// 無法解析驅動器或送出失敗時回傳錯誤
+    pub fn try_drive_homing_start<A: Into<DriveTarget>>(&mut self, drive: A) -> Result<()> {
+        let addr = self.resolve_drive(drive)?;
+        self.try_send_script_to_buffer(&format!("{} {} go", addr.channel, addr.slave))
     }
 
     /// Set Target Velocity (在 PV 模式下會開始運動，SDO)
@@ -264,6 +515,7 @@ impl Botnana {
     /// @position : slave position
     /// @channel  : channel
     /// @velocity : velocity ([pulse/s]，但會因驅動器而異)
+    #[deprecated(note = "use try_set_drive_target_velocity")]
     pub fn set_drive_target_velocity(
         &mut self,
         alias: u16,
@@ -271,12 +523,26 @@ impl Botnana {
         channel: u16,
         velocity: i32,
     ) {
-        self.send_script_to_buffer(&format!(
-            "{} {} {} target-v!",
+        let _ = self.try_set_drive_target_velocity(
+            DriveAddr::new(alias, position, channel),
             velocity,
-            channel,
-            slave_position!(alias, position),
-        ));
+        );
+    }
+
+    /// Set Target Velocity (在 PV 模式下會開始運動，SDO)
+    /// @drive    : 驅動器位址或 axis 名稱
+    /// @velocity : velocity ([pulse/s]，但會因驅動器而異)
+    /// 無法解析驅動器或送出失敗時回傳錯誤
+    pub fn try_set_drive_target_velocity<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        velocity: i32,
+    ) -> Result<()> {
+        let addr = self.resolve_drive(drive)?;
+        self.try_send_script_to_buffer(&format!(
+            "{} {} {} target-v!",
+            velocity, addr.channel, addr.slave
+        ))
     }
 
     /// Set Torque Slope (For TQ Mode, SDO)
@@ -284,13 +550,25 @@ impl Botnana {
     /// @position : slave position
     /// @channel  : channel
     /// @slope : slope ([0.1%/s])
+    #[deprecated(note = "use try_set_drive_torque_slope")]
     pub fn set_drive_torque_slope(&mut self, alias: u16, position: u16, channel: u16, slope: u32) {
-        self.send_script_to_buffer(&format!(
+        let _ = self.try_set_drive_torque_slope(DriveAddr::new(alias, position, channel), slope);
+    }
+
+    /// Set Torque Slope (For TQ Mode, SDO)
+    /// @drive    : 驅動器位址或 axis 名稱
+    /// @slope : slope ([0.1%/s])
+    /// 無法解析驅動器或送出失敗時回傳錯誤
+    pub fn try_set_drive_torque_slope<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        slope: u32,
+    ) -> Result<()> {
+        let addr = self.resolve_drive(drive)?;
+        self.try_send_script_to_buffer(&format!(
             "{} {} {} tq-slope!",
-            slope,
-            channel,
-            slave_position!(alias, position),
-        ));
+            slope, addr.channel, addr.slave
+        ))
     }
 
     /// Set Target Torque (在 TQ 模式下會開始運動，SDO)
@@ -298,6 +576,7 @@ impl Botnana {
     /// @position : slave position
     /// @channel  : channel
     /// @torque : velocity ([0.1%])
+    #[deprecated(note = "use try_set_drive_target_torque")]
     pub fn set_drive_target_torque(
         &mut self,
         alias: u16,
@@ -305,11 +584,124 @@ impl Botnana {
         channel: u16,
         torque: i16,
     ) {
-        self.send_script_to_buffer(&format!(
+        let _ = self.try_set_drive_target_torque(DriveAddr::new(alias, position, channel), torque);
+    }
+
+    /// Set Target Torque (在 TQ 模式下會開始運動，SDO)
+    /// @drive    : 驅動器位址或 axis 名稱
+    /// @torque : velocity ([0.1%])
+    /// 無法解析驅動器或送出失敗時回傳錯誤
+    pub fn try_set_drive_target_torque<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        torque: i16,
+    ) -> Result<()> {
+        let addr = self.resolve_drive(drive)?;
+        self.try_send_script_to_buffer(&format!(
             "{} {} {} target-tq!",
-            torque,
-            channel,
-            slave_position!(alias, position),
-        ));
+            torque, addr.channel, addr.slave
+        ))
+    }
+
+    /// 由 data pool 取得驅動器的 CiA 402 狀態 (需要先啟動自動取得資料的功能)
+    /// 找不到驅動器或 axis 名稱時為 None
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn drive_state<A: Into<DriveTarget>>(&self, drive: A) -> Option<Cia402State> {
+        let snapshot = self.snapshot();
+        snapshot
+            .resolve(&drive.into())
+            .ok()
+            .and_then(|x| snapshot.drive(&x))
+            .map(|x| x.cia402_state())
+    }
+
+    /// Drive On 並等待驅動器進入 Operation Enabled (需要先啟動自動取得資料的功能)
+    /// 依 data pool 中的 status word 判斷狀態，狀態改變或每隔一段時間仍未到達時送出 drive-on，
+    /// 逾時或 Fault 時回傳 `Error::DriveState`，包含最後停留的狀態
+    /// 不可在 callback 內呼叫，data pool 由處理 callback 的 thread 更新
+    /// @drive       : 驅動器位址或 axis 名稱
+    /// @reset_fault : 遇到 Fault 時是否先清除驅動器異警 (只清除一次)
+    /// @timeout     : 等待的時間
+    pub fn drive_on_and_wait<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        reset_fault: bool,
+        timeout: Duration,
+    ) -> Result<()> {
+        let addr = self.resolve_drive(drive)?;
+        let deadline = Instant::now() + timeout;
+        let mut sequence = DriveOnSequence::new(reset_fault);
+        loop {
+            let state = self.drive_state(addr);
+            match sequence.step(state, Instant::now())? {
+                DriveOnStep::Done => return Ok(()),
+                DriveOnStep::Send(word) => {
+                    self.try_send_script_to_buffer(&format!(
+                        "{} {} {}",
+                        addr.channel, addr.slave, word
+                    ))?;
+                }
+                DriveOnStep::Wait => {}
+            }
+            if Instant::now() >= deadline {
+                return Err(state.map_or(Error::DriveNotFound, Error::DriveState));
+            }
+            thread::sleep(Duration::from_millis(DRIVE_POLL_INTERVAL_MS));
+        }
+    }
+
+    /// 由 data pool 取得驅動器目前的 operation mode (0x6061，需要先啟動自動取得資料的功能)
+    /// 還沒收到、不支援的 mode 或找不到驅動器時為 None
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn drive_operation_mode<A: Into<DriveTarget>>(&self, drive: A) -> Option<OperationMode> {
+        let snapshot = self.snapshot();
+        snapshot
+            .resolve(&drive.into())
+            .ok()
+            .and_then(|x| snapshot.drive(&x))
+            .and_then(|x| x.operation_mode())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drive_on_sequence() {
+        let start = Instant::now();
+        let mut sequence = DriveOnSequence::new(false);
+        // 還沒收到驅動器資料
+        assert_eq!(sequence.step(None, start), Ok(DriveOnStep::Wait));
+        let disabled = Some(Cia402State::SwitchOnDisabled);
+        assert_eq!(sequence.step(disabled, start), Ok(DriveOnStep::Send("drive-on")));
+        // 狀態沒有改變時，間隔到了才重送
+        let later = start + Duration::from_millis(DRIVE_ON_RESEND_INTERVAL_MS / 2);
+        assert_eq!(sequence.step(disabled, later), Ok(DriveOnStep::Wait));
+        let later = start + Duration::from_millis(DRIVE_ON_RESEND_INTERVAL_MS);
+        assert_eq!(sequence.step(disabled, later), Ok(DriveOnStep::Send("drive-on")));
+        // 狀態改變時立即送出
+        let ready = Some(Cia402State::ReadyToSwitchOn);
+        assert_eq!(sequence.step(ready, later), Ok(DriveOnStep::Send("drive-on")));
+        assert_eq!(
+            sequence.step(Some(Cia402State::OperationEnabled), later),
+            Ok(DriveOnStep::Done)
+        );
+    }
+
+    #[test]
+    fn drive_on_sequence_fault() {
+        let now = Instant::now();
+        let fault = Some(Cia402State::Fault);
+        let mut sequence = DriveOnSequence::new(false);
+        assert_eq!(sequence.step(fault, now), Err(Error::DriveState(Cia402State::Fault)));
+
+        // 只清除一次異警，清除後等待狀態改變
+        let mut sequence = DriveOnSequence::new(true);
+        assert_eq!(sequence.step(fault, now), Ok(DriveOnStep::Send("reset-fault")));
+        assert_eq!(sequence.step(fault, now), Ok(DriveOnStep::Wait));
+        let disabled = Some(Cia402State::SwitchOnDisabled);
+        assert_eq!(sequence.step(disabled, now), Ok(DriveOnStep::Send("drive-on")));
+        assert_eq!(sequence.step(fault, now), Err(Error::DriveState(Cia402State::Fault)));
     }
 }
@@ -0,0 +1,97 @@
+use cia402::{Cia402State, OperationMode};
+use serde_json::Value;
+use std::{self, fmt};
+use topology::TopologyDiff;
+
+/// Botnana API 的錯誤類型
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// 尚未連線到 Botnana
+    NotConnected,
+    /// 訊息送出失敗
+    SendFailed(String),
+    /// 等待回應逾時
+    Timeout,
+    /// Server 回傳的 JSON-RPC error
+    Rpc { code: i64, message: String },
+    /// Forth 命令執行中止 (abort)
+    ForthAbort(String),
+    /// 無法解析收到的資料
+    Parse(String),
+    /// WebSocket 連線錯誤
+    Connection(String),
+    /// Data pool 中找不到指定的驅動器 (需要先啟動自動取得資料的功能)
+    DriveNotFound,
+    /// 驅動器停在此狀態，無法完成要求的動作
+    DriveState(Cia402State),
+    /// 回歸原點失敗 (status word homing error)
+    HomingFailed,
+    /// 運動中發生 following error (status word bit 13)
+    FollowingError,
+    /// 驅動器不在此命令需要的 operation mode，內容為目前的 mode
+    OperationMode(Option<OperationMode>),
+    /// Data pool 中沒有此 axis 名稱的設定
+    AxisNotFound(String),
+    /// Data pool 中找不到指定的從站 (需要先啟動自動取得資料的功能)
+    SlaveNotFound,
+    /// 從站 AL status 的 error indication，內容為 slave position
+    AlStateError(u16),
+    /// Object dictionary 中沒有此 object，或資料型態、存取權限不符 (參考 `esi`)
+    InvalidObject(String),
+    /// SDO 傳輸中止，內容為 SDO abort code
+    SdoAbort(u32),
+    /// EtherCAT 拓樸與預期不同
+    TopologyMismatch(Vec<TopologyDiff>),
+    /// 系統 I/O 錯誤 (例如無法建立 thread)
+    Io(String),
+}
+
+/// Botnana API 的 Result
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// 由 JSON-RPC 的 error 物件建立
+    pub(crate) fn from_rpc_error(error: &Value) -> Error {
+        match error.get("code").and_then(|x| x.as_i64()) {
+            Some(code) => Error::Rpc {
+                code,
+                message: error
+                    .get("message")
+                    .and_then(|x| x.as_str())
+                    .unwrap_or("")
+                    .to_owned(),
+            },
+            None => Error::Parse(format!("Invalid JSON-RPC error: {}", error)),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::NotConnected => write!(f, "Not connected"),
+            Error::SendFailed(ref e) => write!(f, "Send Message Error: {}", e),
+            Error::Timeout => write!(f, "Timeout"),
+            Error::Rpc { code, ref message } => write!(f, "JSON-RPC Error ({}): {}", code, message),
+            Error::ForthAbort(ref e) => write!(f, "Forth Abort: {}", e),
+            Error::Parse(ref e) => write!(f, "Parse Error: {}", e),
+            Error::Connection(ref e) => write!(f, "{}", e),
+            Error::DriveNotFound => write!(f, "Drive not found in data pool"),
+            Error::DriveState(state) => write!(f, "Drive stuck in {:?}", state),
+            Error::HomingFailed => write!(f, "Homing failed"),
+            Error::FollowingError => write!(f, "Following error"),
+            Error::OperationMode(mode) => write!(f, "Wrong operation mode: {:?}", mode),
+            Error::AxisNotFound(ref e) => write!(f, "Axis not found: {}", e),
+            Error::SlaveNotFound => write!(f, "Slave not found in data pool"),
+            Error::AlStateError(position) => write!(f, "AL state error on slave {}", position),
+            Error::InvalidObject(ref e) => write!(f, "Invalid object: {}", e),
+            Error::SdoAbort(code) => write!(f, "SDO Abort: 0x{:08X}", code),
+            Error::TopologyMismatch(ref diffs) => {
+                write!(f, "Topology mismatch: {} difference(s)", diffs.len())
+            }
+            Error::Io(ref e) => write!(f, "IO Error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,700 @@
+use address::{DriveAddr, SlaveAddr};
+use botnana::Botnana;
+use data_pool::Slave;
+use error::{Error, Result};
+use roxmltree::{Document, Node};
+use sdo::DataType;
+use std::{collections::HashMap, fs, path::Path, sync::Arc};
+
+/// Object dictionary 中的一個 entry (index, subindex)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EsiObject {
+    /// Object index
+    pub index: u16,
+    /// Object subindex，沒有 subindex 的 object 為 0
+    pub subindex: u8,
+    /// 名稱
+    pub name: String,
+    /// ESI 的資料型態名稱 (例如 `UINT`, `DINT`)
+    pub type_name: String,
+    /// 資料長度 [bit]
+    pub bit_size: u32,
+    /// 存取權限 (例如 `ro`, `rw`)
+    pub access: String,
+    /// 可 mapping 到的 PDO (`R`: RxPDO, `T`: TxPDO)，不可 mapping 時為空字串
+    pub pdo_mapping: String,
+}
+
+impl EsiObject {
+    /// 對應的 SDO 資料型態，無法對應時為 None
+    pub fn data_type(&self) -> Option<DataType> {
+        esi_data_type(&self.type_name)
+    }
+
+    /// 是否可寫入
+    pub fn is_writable(&self) -> bool {
+        self.access.contains('w')
+    }
+
+    /// 是否可 mapping 到 RxPDO
+    pub fn is_rx_mappable(&self) -> bool {
+        self.pdo_mapping.to_uppercase().contains('R')
+    }
+
+    /// 是否可 mapping 到 TxPDO
+    pub fn is_tx_mappable(&self) -> bool {
+        self.pdo_mapping.to_uppercase().contains('T')
+    }
+}
+
+/// PDO 中的一個 entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdoEntry {
+    /// Object index，0 表示 padding
+    pub index: u16,
+    /// Object subindex
+    pub subindex: u8,
+    /// 資料長度 [bit]
+    pub bit_len: u32,
+    /// 名稱
+    pub name: String,
+    /// ESI 的資料型態名稱
+    pub type_name: String,
+}
+
+/// ESI 中描述的 PDO
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pdo {
+    /// PDO index (例如 0x1600, 0x1A00)
+    pub index: u16,
+    /// 名稱
+    pub name: String,
+    /// 預設指派的 sync manager，沒有預設指派時為 None
+    pub sm: Option<u8>,
+    /// 內容是否固定不可修改
+    pub fixed: bool,
+    /// PDO entries
+    pub entries: Vec<PdoEntry>,
+}
+
+impl Pdo {
+    /// 是否為預設的 PDO mapping
+    pub fn is_default(&self) -> bool {
+        self.sm.is_some()
+    }
+}
+
+/// ESI 中描述的裝置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EsiDevice {
+    /// Vendor ID
+    pub vendor_id: u32,
+    /// Product Code
+    pub product_code: u32,
+    /// Revision number
+    pub revision: u32,
+    /// 型號 (`Type` 的內容)
+    pub type_name: String,
+    /// 裝置名稱
+    pub name: String,
+    /// Object dictionary，ESI 沒有描述時為空
+    pub objects: Vec<EsiObject>,
+    /// RxPDO (主站到從站)
+    pub rx_pdos: Vec<Pdo>,
+    /// TxPDO (從站到主站)
+    pub tx_pdos: Vec<Pdo>,
+}
+
+impl EsiDevice {
+    /// 取得 object dictionary 中的 entry
+    pub fn object(&self, index: u16, subindex: u8) -> Option<&EsiObject> {
+        self.objects
+            .iter()
+            .find(|x| x.index == index && x.subindex == subindex)
+    }
+
+    /// 預設的 RxPDO mapping
+    pub fn default_rx_pdos(&self) -> impl Iterator<Item = &Pdo> {
+        self.rx_pdos.iter().filter(|x| x.is_default())
+    }
+
+    /// 預設的 TxPDO mapping
+    pub fn default_tx_pdos(&self) -> impl Iterator<Item = &Pdo> {
+        self.tx_pdos.iter().filter(|x| x.is_default())
+    }
+
+    /// 依 object dictionary 檢查 SDO 的資料型態與存取權限
+    /// ESI 沒有描述的 object 不檢查，只有已知 object 的型態或權限不符時回傳 `Error::InvalidObject`
+    /// @index     : object index
+    /// @subindex  : object subindex
+    /// @data_type : 資料型態
+    /// @write     : 是否為寫入
+    pub fn validate_sdo(
+        &self,
+        index: u16,
+        subindex: u8,
+        data_type: DataType,
+        write: bool,
+    ) -> Result<()> {
+        let object = match self.object(index, subindex) {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+        let mismatch = match object.data_type() {
+            Some(x) => x != data_type,
+            // SDO 不支援的型態 (例如 LREAL、STRING) 只比較長度
+            None => object.bit_size != 0 && object.bit_size as usize != data_type.size() * 8,
+        };
+        if mismatch {
+            Err(Error::InvalidObject(format!(
+                "0x{:04X}:{:02X} of {} is {}, not {:?}",
+                index, subindex, self.type_name, object.type_name, data_type
+            )))
+        } else if write && !object.is_writable() {
+            Err(Error::InvalidObject(format!(
+                "0x{:04X}:{:02X} of {} is not writable",
+                index, subindex, self.type_name
+            )))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// 依 object dictionary 檢查 object 可否 mapping 到 PDO，ESI 沒有描述的 object 不檢查
+    /// @index     : object index
+    /// @subindex  : object subindex
+    /// @rx        : RxPDO 或是 TxPDO
+    pub fn validate_pdo_entry(&self, index: u16, subindex: u8, rx: bool) -> Result<()> {
+        let object = match self.object(index, subindex) {
+            Some(x) if index != 0 => x,
+            _ => return Ok(()),
+        };
+        let mappable = if rx {
+            object.is_rx_mappable()
+        } else {
+            object.is_tx_mappable()
+        };
+        if mappable {
+            Ok(())
+        } else {
+            Err(Error::InvalidObject(format!(
+                "0x{:04X}:{:02X} of {} can not be mapped to {}",
+                index,
+                subindex,
+                self.type_name,
+                if rx { "RxPDO" } else { "TxPDO" }
+            )))
+        }
+    }
+}
+
+/// EtherCAT Slave Information (ESI) 的裝置描述集合
+#[derive(Debug, Clone, Default)]
+pub struct EsiLibrary {
+    devices: Vec<EsiDevice>,
+}
+
+impl EsiLibrary {
+    /// New
+    pub fn new() -> EsiLibrary {
+        EsiLibrary::default()
+    }
+
+    /// 讀取目錄中所有的 ESI 檔 (`*.xml`)，不包含子目錄
+    pub fn load_dir<P: AsRef<Path>>(path: P) -> Result<EsiLibrary> {
+        let path = path.as_ref();
+        let entries =
+            fs::read_dir(path).map_err(|e| Error::Parse(format!("{}: {}", path.display(), e)))?;
+        let mut library = EsiLibrary::new();
+        for entry in entries {
+            let file = entry
+                .map_err(|e| Error::Parse(format!("{}: {}", path.display(), e)))?
+                .path();
+            let is_xml = file
+                .extension()
+                .and_then(|x| x.to_str())
+                .map(|x| x.eq_ignore_ascii_case("xml"))
+                .unwrap_or(false);
+            if is_xml && file.is_file() {
+                library.load_file(&file)?;
+            }
+        }
+        Ok(library)
+    }
+
+    /// 讀取 ESI 檔
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|e| Error::Parse(format!("{}: {}", path.display(), e)))?;
+        self.parse(&String::from_utf8_lossy(&bytes))
+            .map_err(|e| Error::Parse(format!("{}: {}", path.display(), e)))
+    }
+
+    /// 解析 ESI 的 XML 內容
+    pub fn parse(&mut self, xml: &str) -> Result<()> {
+        let doc = Document::parse(xml.trim_start_matches('\u{feff}'))
+            .map_err(|e| Error::Parse(e.to_string()))?;
+        let root = doc.root_element();
+        let vendor_id = child(root, "Vendor")
+            .and_then(|x| child_number(x, "Id"))
+            .ok_or_else(|| Error::Parse("ESI without vendor id".to_owned()))?;
+        self.devices.extend(
+            root.descendants()
+                .filter(|x| x.has_tag_name("Device"))
+                .filter_map(|x| parse_device(vendor_id as u32, x)),
+        );
+        Ok(())
+    }
+
+    /// 所有的裝置
+    pub fn devices(&self) -> &[EsiDevice] {
+        &self.devices
+    }
+
+    /// 依 vendor ID 與 product code 取得裝置，有多個 revision 時取最新的
+    pub fn device(&self, vendor_id: u32, product_code: u32) -> Option<&EsiDevice> {
+        self.devices
+            .iter()
+            .filter(|x| x.vendor_id == vendor_id && x.product_code == product_code)
+            .max_by_key(|x| x.revision)
+    }
+
+    /// 取得 data pool 中從站對應的裝置
+    pub fn device_for(&self, slave: &Slave) -> Option<&EsiDevice> {
+        self.device(slave.vendor_id, slave.product_code)
+    }
+}
+
+/// ESI 資料型態對應的 SDO 資料型態
+fn esi_data_type(name: &str) -> Option<DataType> {
+    match name {
+        "SINT" => Some(DataType::I8),
+        "INT" => Some(DataType::I16),
+        "DINT" => Some(DataType::I32),
+        "USINT" | "BYTE" | "BOOL" => Some(DataType::U8),
+        "UINT" | "WORD" => Some(DataType::U16),
+        "UDINT" | "DWORD" => Some(DataType::U32),
+        "REAL" => Some(DataType::F32),
+        _ => None,
+    }
+}
+
+/// ESI 的數字，`#x` 開頭為 hex
+fn parse_number(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if text.starts_with("#x") || text.starts_with("#X") {
+        u64::from_str_radix(&text[2..], 16).ok()
+    } else {
+        text.parse::<u64>().ok()
+    }
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|x| x.has_tag_name(name))
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> &'a str {
+    child(node, name)
+        .and_then(|x| x.text())
+        .map(|x| x.trim())
+        .unwrap_or("")
+}
+
+fn child_number(node: Node, name: &str) -> Option<u64> {
+    child(node, name).and_then(|x| x.text()).and_then(parse_number)
+}
+
+/// `Flags` 中的 access 與 PDO mapping，沒有時使用 `default`
+fn flags<'a>(node: Node<'a, '_>, default: (&'a str, &'a str)) -> (&'a str, &'a str) {
+    match child(node, "Flags") {
+        Some(flags) => (
+            child(flags, "Access")
+                .and_then(|x| x.text())
+                .unwrap_or(default.0),
+            child(flags, "PdoMapping")
+                .and_then(|x| x.text())
+                .unwrap_or(default.1),
+        ),
+        None => default,
+    }
+}
+
+fn parse_device(vendor_id: u32, device: Node) -> Option<EsiDevice> {
+    let device_type = child(device, "Type")?;
+    let product_code = device_type.attribute("ProductCode").and_then(parse_number)?;
+    let revision = device_type
+        .attribute("RevisionNo")
+        .and_then(parse_number)
+        .unwrap_or(0);
+    Some(EsiDevice {
+        vendor_id,
+        product_code: product_code as u32,
+        revision: revision as u32,
+        type_name: device_type.text().unwrap_or("").trim().to_owned(),
+        name: child_text(device, "Name").to_owned(),
+        objects: device
+            .descendants()
+            .filter(|x| x.has_tag_name("Dictionary"))
+            .flat_map(parse_dictionary)
+            .collect(),
+        rx_pdos: parse_pdos(device, "RxPdo"),
+        tx_pdos: parse_pdos(device, "TxPdo"),
+    })
+}
+
+/// 解析 `Dictionary`，有 subindex 的 object 依 `DataTypes` 中的 `SubItem` 展開
+fn parse_dictionary(dictionary: Node) -> Vec<EsiObject> {
+    let data_types: HashMap<&str, Node> = child(dictionary, "DataTypes")
+        .map(|x| {
+            x.children()
+                .filter(|x| x.has_tag_name("DataType"))
+                .map(|x| (child_text(x, "Name"), x))
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut objects = Vec::new();
+    let list = match child(dictionary, "Objects") {
+        Some(x) => x,
+        None => return objects,
+    };
+    for object in list.children().filter(|x| x.has_tag_name("Object")) {
+        let index = match child_number(object, "Index") {
+            Some(x) => x as u16,
+            None => continue,
+        };
+        let (access, pdo_mapping) = flags(object, ("", ""));
+        let type_name = child_text(object, "Type");
+        let sub_items = data_types
+            .get(type_name)
+            .filter(|x| child(**x, "SubItem").is_some());
+        match sub_items {
+            Some(data_type) => {
+                for item in data_type.children().filter(|x| x.has_tag_name("SubItem")) {
+                    let (access, pdo_mapping) = flags(item, (access, pdo_mapping));
+                    let item_type = child_text(item, "Type");
+                    let name = child_text(item, "Name");
+                    let bit_size = child_number(item, "BitSize").unwrap_or(0) as u32;
+                    let array = data_types.get(item_type).and_then(|x| child(*x, "ArrayInfo"));
+                    if let Some(array) = array {
+                        // 陣列展開成連續的 subindex
+                        let lbound = child_number(array, "LBound").unwrap_or(1);
+                        let elements = child_number(array, "Elements").unwrap_or(0);
+                        for i in 0..elements {
+                            objects.push(EsiObject {
+                                index,
+                                subindex: (lbound + i) as u8,
+                                name: format!("{} {}", name, lbound + i),
+                                type_name: child_text(data_types[item_type], "BaseType").to_owned(),
+                                bit_size: bit_size / elements as u32,
+                                access: access.to_owned(),
+                                pdo_mapping: pdo_mapping.to_owned(),
+                            });
+                        }
+                    } else if let Some(subindex) = child_number(item, "SubIdx") {
+                        objects.push(EsiObject {
+                            index,
+                            subindex: subindex as u8,
+                            name: name.to_owned(),
+                            type_name: item_type.to_owned(),
+                            bit_size,
+                            access: access.to_owned(),
+                            pdo_mapping: pdo_mapping.to_owned(),
+                        });
+                    }
+                }
+            }
+            None => objects.push(EsiObject {
+                index,
+                subindex: 0,
+                name: child_text(object, "Name").to_owned(),
+                type_name: type_name.to_owned(),
+                bit_size: child_number(object, "BitSize").unwrap_or(0) as u32,
+                access: access.to_owned(),
+                pdo_mapping: pdo_mapping.to_owned(),
+            }),
+        }
+    }
+    objects
+}
+
+fn parse_pdos(device: Node, tag: &str) -> Vec<Pdo> {
+    device
+        .children()
+        .filter(|x| x.has_tag_name(tag))
+        .filter_map(|pdo| {
+            Some(Pdo {
+                index: child_number(pdo, "Index")? as u16,
+                name: child_text(pdo, "Name").to_owned(),
+                sm: pdo.attribute("Sm").and_then(parse_number).map(|x| x as u8),
+                fixed: matches!(pdo.attribute("Fixed"), Some("1") | Some("true")),
+                entries: pdo
+                    .children()
+                    .filter(|x| x.has_tag_name("Entry"))
+                    .filter_map(|entry| {
+                        Some(PdoEntry {
+                            index: child_number(entry, "Index")? as u16,
+                            subindex: child_number(entry, "SubIndex").unwrap_or(0) as u8,
+                            bit_len: child_number(entry, "BitLen")? as u32,
+                            name: child_text(entry, "Name").to_owned(),
+                            type_name: child_text(entry, "DataType").to_owned(),
+                        })
+                    })
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+impl Botnana {
+    /// 設定 ESI library，之後 SDO API 會依從站的 object dictionary 檢查 index 與資料型態
+    pub fn set_esi_library(&mut self, library: EsiLibrary) {
+        *self.esi.lock().expect("set_esi_library") = Some(Arc::new(library));
+    }
+
+    /// 取得從站對應的 ESI 裝置 (需要先啟動自動取得資料的功能)
+    /// @slave    : slave address
+    pub fn esi_device(&self, slave: SlaveAddr) -> Option<EsiDevice> {
+        self.with_esi_device(&slave, |x| x.clone())
+    }
+
+    /// SDO 傳輸前依 ESI 檢查，沒有設定 ESI library 或找不到對應的裝置時不檢查
+    pub(crate) fn validate_sdo(
+        &self,
+        addr: &DriveAddr,
+        index: u16,
+        subindex: u8,
+        data_type: DataType,
+        write: bool,
+    ) -> Result<()> {
+        self.with_esi_device(&addr.slave, |x| x.validate_sdo(index, subindex, data_type, write))
+            .unwrap_or(Ok(()))
+    }
+
+    fn with_esi_device<R, F: FnOnce(&EsiDevice) -> R>(&self, slave: &SlaveAddr, f: F) -> Option<R> {
+        let library = self.esi.lock().expect("with_esi_device").clone()?;
+        let snapshot = self.snapshot();
+        snapshot
+            .slave(slave)
+            .and_then(|x| library.device_for(x))
+            .map(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ESI: &str = r##"<?xml version="1.0" encoding="utf-8"?>
+<EtherCATInfo>
+  <Vendor>
+    <Id>#x0000066F</Id>
+    <Name>Vendor</Name>
+  </Vendor>
+  <Descriptions>
+    <Devices>
+      <Device>
+        <Type ProductCode="#x10305070" RevisionNo="#x00000002">D1</Type>
+        <Name>Servo Drive</Name>
+        <Profile>
+          <Dictionary>
+            <DataTypes>
+              <DataType>
+                <Name>DT1600ARR</Name>
+                <BaseType>UDINT</BaseType>
+                <BitSize>64</BitSize>
+                <ArrayInfo>
+                  <LBound>1</LBound>
+                  <Elements>2</Elements>
+                </ArrayInfo>
+              </DataType>
+              <DataType>
+                <Name>DT1600</Name>
+                <BitSize>80</BitSize>
+                <SubItem>
+                  <SubIdx>0</SubIdx>
+                  <Name>SubIndex 000</Name>
+                  <Type>USINT</Type>
+                  <BitSize>8</BitSize>
+                  <Flags>
+                    <Access>ro</Access>
+                  </Flags>
+                </SubItem>
+                <SubItem>
+                  <Name>Elements</Name>
+                  <Type>DT1600ARR</Type>
+                  <BitSize>64</BitSize>
+                </SubItem>
+              </DataType>
+            </DataTypes>
+            <Objects>
+              <Object>
+                <Index>#x1600</Index>
+                <Name>RxPDO 1</Name>
+                <Type>DT1600</Type>
+                <Flags>
+                  <Access>rw</Access>
+                </Flags>
+              </Object>
+              <Object>
+                <Index>#x6040</Index>
+                <Name>Controlword</Name>
+                <Type>UINT</Type>
+                <BitSize>16</BitSize>
+                <Flags>
+                  <Access>rw</Access>
+                  <PdoMapping>R</PdoMapping>
+                </Flags>
+              </Object>
+              <Object>
+                <Index>#x6041</Index>
+                <Name>Statusword</Name>
+                <Type>UINT</Type>
+                <BitSize>16</BitSize>
+                <Flags>
+                  <Access>ro</Access>
+                  <PdoMapping>T</PdoMapping>
+                </Flags>
+              </Object>
+              <Object>
+                <Index>24676</Index>
+                <Name>Position actual value</Name>
+                <Type>DINT</Type>
+                <BitSize>32</BitSize>
+                <Flags>
+                  <Access>ro</Access>
+                  <PdoMapping>T</PdoMapping>
+                </Flags>
+              </Object>
+              <Object>
+                <Index>#x2000</Index>
+                <Name>Gain</Name>
+                <Type>LREAL</Type>
+                <BitSize>64</BitSize>
+                <Flags>
+                  <Access>rw</Access>
+                </Flags>
+              </Object>
+            </Objects>
+          </Dictionary>
+        </Profile>
+        <RxPdo Fixed="1" Sm="2">
+          <Index>#x1600</Index>
+          <Name>RxPDO 1</Name>
+          <Entry>
+            <Index>#x6040</Index>
+            <SubIndex>0</SubIndex>
+            <BitLen>16</BitLen>
+            <Name>Controlword</Name>
+            <DataType>UINT</DataType>
+          </Entry>
+          <Entry>
+            <Index>#x0</Index>
+            <BitLen>16</BitLen>
+          </Entry>
+        </RxPdo>
+        <TxPdo>
+          <Index>#x1A00</Index>
+          <Name>TxPDO 1</Name>
+          <Entry>
+            <Index>#x6041</Index>
+            <SubIndex>0</SubIndex>
+            <BitLen>16</BitLen>
+            <Name>Statusword</Name>
+            <DataType>UINT</DataType>
+          </Entry>
+        </TxPdo>
+      </Device>
+    </Devices>
+  </Descriptions>
+</EtherCATInfo>
+"##;
+
+    fn device() -> EsiDevice {
+        let mut library = EsiLibrary::new();
+        library.parse(ESI).unwrap();
+        library.device(0x66F, 0x1030_5070).cloned().unwrap()
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse_number("#x1A00"), Some(0x1A00));
+        assert_eq!(parse_number(" #X10 "), Some(0x10));
+        assert_eq!(parse_number("24676"), Some(24676));
+        assert_eq!(parse_number("x10"), None);
+    }
+
+    #[test]
+    fn parse_device() {
+        let device = device();
+        assert_eq!(device.revision, 2);
+        assert_eq!(device.type_name, "D1");
+        assert_eq!(device.name, "Servo Drive");
+        let object = device.object(0x6064, 0).unwrap();
+        assert_eq!(object.data_type(), Some(DataType::I32));
+        assert_eq!(object.bit_size, 32);
+        // SDO 不支援 64 bit 型態
+        assert_eq!(device.object(0x2000, 0).and_then(|x| x.data_type()), None);
+    }
+
+    #[test]
+    fn parse_sub_items() {
+        let device = device();
+        let sub0 = device.object(0x1600, 0).unwrap();
+        assert_eq!(sub0.type_name, "USINT");
+        // SubItem 自己的 Flags
+        assert!(!sub0.is_writable());
+        // 陣列展開成 subindex 1, 2，沒有 Flags 時使用 object 的 Flags
+        for subindex in 1..3 {
+            let item = device.object(0x1600, subindex).unwrap();
+            assert_eq!(item.type_name, "UDINT");
+            assert_eq!(item.bit_size, 32);
+            assert!(item.is_writable());
+        }
+        assert_eq!(device.object(0x1600, 3), None);
+    }
+
+    #[test]
+    fn parse_pdos() {
+        let device = device();
+        let rx: Vec<&Pdo> = device.default_rx_pdos().collect();
+        assert_eq!(rx.len(), 1);
+        assert_eq!(rx[0].index, 0x1600);
+        assert_eq!(rx[0].sm, Some(2));
+        assert!(rx[0].fixed);
+        assert_eq!(rx[0].entries.len(), 2);
+        assert_eq!(rx[0].entries[0].index, 0x6040);
+        assert_eq!(rx[0].entries[1].index, 0);
+        assert_eq!(rx[0].entries[1].bit_len, 16);
+        assert_eq!(device.default_tx_pdos().count(), 0);
+        assert_eq!(device.tx_pdos.len(), 1);
+        assert!(!device.tx_pdos[0].fixed);
+    }
+
+    #[test]
+    fn validate_sdo() {
+        let device = device();
+        assert_eq!(device.validate_sdo(0x6040, 0, DataType::U16, true), Ok(()));
+        assert_eq!(device.validate_sdo(0x6041, 0, DataType::U16, false), Ok(()));
+        // 不支援的型態只比較長度
+        assert!(device.validate_sdo(0x2000, 0, DataType::F32, true).is_err());
+        // 型態不符
+        assert!(device.validate_sdo(0x6041, 0, DataType::I32, false).is_err());
+        // 唯讀
+        assert!(device.validate_sdo(0x6041, 0, DataType::U16, true).is_err());
+        // ESI 沒有描述的 object 不檢查
+        assert_eq!(device.validate_sdo(0x6060, 0, DataType::I8, true), Ok(()));
+    }
+
+    #[test]
+    fn validate_pdo_entry() {
+        let device = device();
+        assert_eq!(device.validate_pdo_entry(0x6040, 0, true), Ok(()));
+        assert_eq!(device.validate_pdo_entry(0x6041, 0, false), Ok(()));
+        assert!(device.validate_pdo_entry(0x6040, 0, false).is_err());
+        assert!(device.validate_pdo_entry(0x6041, 0, true).is_err());
+        assert!(device.validate_pdo_entry(0x2000, 0, true).is_err());
+        // padding 與 ESI 沒有描述的 object 不檢查
+        assert_eq!(device.validate_pdo_entry(0, 0, true), Ok(()));
+        assert_eq!(device.validate_pdo_entry(0x6060, 0, true), Ok(()));
+    }
+}
@@ -1,4 +1,14 @@
+use address::SlaveAddr;
+use al_state::AlState;
 use botnana::Botnana;
+use error::{Error, Result};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// 檢查 AL state 的間隔
+const AL_STATE_POLL_INTERVAL_MS: u64 = 10;
 
 impl Botnana {
     /// Request EtherCAT link status
@@ -6,17 +16,164 @@ impl Botnana {
         self.evaluate(".ec-links");
     }
 
+    /// Request EtherCAT link status
+    /// 未連線或送出失敗時回傳錯誤
+    pub fn try_request_ec_link_status(&mut self) -> Result<()> {
+        self.send_evaluate(".ec-links")
+    }
+
     /// Request EtherCAT Slave Info.
     /// @alias    : slave alias
     /// @position : slave position
+    #[deprecated(note = "use try_request_ec_slave_info")]
     pub fn request_ec_slave_info(&mut self, alias: u16, position: u16) {
-        self.evaluate(&format!("{} .slave", slave_position!(alias, position)));
+        self.evaluate(&format!("{} .slave", SlaveAddr::new(alias, position)));
+    }
+
+    /// Request EtherCAT Slave Info.
+    /// 未連線或送出失敗時回傳錯誤
+    /// @slave    : slave address
+    pub fn try_request_ec_slave_info(&mut self, slave: SlaveAddr) -> Result<()> {
+        self.send_evaluate(&format!("{} .slave", slave))
     }
 
     /// Request EtherCAT Slave Info. (只回傳與上次要求不同的狀態)
     /// @alias    : slave alias
     /// @position : slave position
+    #[deprecated(note = "use try_request_ec_slave_info_diff")]
     pub fn request_ec_slave_info_diff(&mut self, alias: u16, position: u16) {
-        self.evaluate(&format!("{} .slave_diff", slave_position!(alias, position)));
+        self.evaluate(&format!("{} .slave_diff", SlaveAddr::new(alias, position)));
+    }
+
+    /// Request EtherCAT Slave Info. (只回傳與上次要求不同的狀態)
+    /// 未連線或送出失敗時回傳錯誤
+    /// @slave    : slave address
+    pub fn try_request_ec_slave_info_diff(&mut self, slave: SlaveAddr) -> Result<()> {
+        self.send_evaluate(&format!("{} .slave_diff", slave))
+    }
+
+    /// 等待從站到達指定的 AL state (需要先啟動自動取得資料的功能)
+    /// 從站的 AL status 出現 error indication 時回傳 `Error::AlStateError`，
+    /// 開始等待前就已存在的 error indication 不視為錯誤，但在清除前不算到達
+    /// 還沒收到從站資料時繼續等待，逾時才回傳 `Error::SlaveNotFound`
+    /// 不可在 callback 內呼叫，data pool 由處理 callback 的 thread 更新
+    /// @slave    : slave address，None 表示所有從站
+    /// @state    : 等待的 AL state
+    /// @timeout  : 等待的時間
+    pub fn ec_wait_state<S: Into<Option<SlaveAddr>>>(
+        &self,
+        slave: S,
+        state: AlState,
+        timeout: Duration,
+    ) -> Result<()> {
+        let slave = slave.into();
+        let deadline = Instant::now() + timeout;
+        let mut previous_errors = None;
+        loop {
+            let snapshot = self.snapshot();
+            let positions: Vec<usize> = match slave {
+                Some(ref addr) => snapshot.slave_index(addr).into_iter().collect(),
+                None => (1..snapshot.slaves.len()).collect(),
+            };
+            if positions.is_empty() {
+                if Instant::now() >= deadline {
+                    return Err(Error::SlaveNotFound);
+                }
+                thread::sleep(Duration::from_millis(AL_STATE_POLL_INTERVAL_MS));
+                continue;
+            }
+            let previous_errors = previous_errors.get_or_insert_with(|| {
+                positions
+                    .iter()
+                    .filter(|x| snapshot.slaves[**x].has_al_error())
+                    .cloned()
+                    .collect::<Vec<_>>()
+            });
+            let mut reached = true;
+            for position in positions {
+                let slave = &snapshot.slaves[position];
+                if slave.has_al_error() && !previous_errors.contains(&position) {
+                    return Err(Error::AlStateError(position as u16));
+                }
+                reached = reached && !slave.has_al_error() && slave.al_state() == Some(state);
+            }
+            if reached {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(Error::Timeout);
+            }
+            thread::sleep(Duration::from_millis(AL_STATE_POLL_INTERVAL_MS));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_pool::Slave;
+
+    /// 設定從站的 AL status 並發布快照
+    fn set_slaves(botnana: &Botnana, states: &[u8]) {
+        {
+            let mut data_pool = botnana.data_pool.lock().unwrap();
+            data_pool.slaves = vec![Slave::new()];
+            for state in states {
+                let mut slave = Slave::new();
+                slave.ec_state = *state;
+                data_pool.slaves.push(slave);
+            }
+        }
+        botnana.publish_snapshot();
+    }
+
+    #[test]
+    fn wait_state_reached() {
+        let botnana = Botnana::new();
+        set_slaves(&botnana, &[0x08, 0x08]);
+        let timeout = Duration::from_millis(0);
+        assert_eq!(botnana.ec_wait_state(None, AlState::Op, timeout), Ok(()));
+        set_slaves(&botnana, &[0x08, 0x04]);
+        assert_eq!(botnana.ec_wait_state(SlaveAddr::Position(1), AlState::Op, timeout), Ok(()));
+        assert_eq!(botnana.ec_wait_state(None, AlState::Op, timeout), Err(Error::Timeout));
+    }
+
+    #[test]
+    fn wait_state_without_slaves() {
+        let botnana = Botnana::new();
+        let timeout = Duration::from_millis(20);
+        assert_eq!(
+            botnana.ec_wait_state(None, AlState::Op, timeout),
+            Err(Error::SlaveNotFound)
+        );
+        set_slaves(&botnana, &[0x08]);
+        assert_eq!(
+            botnana.ec_wait_state(SlaveAddr::Position(2), AlState::Op, timeout),
+            Err(Error::SlaveNotFound)
+        );
+    }
+
+    #[test]
+    fn wait_state_error_indication() {
+        let botnana = Botnana::new();
+        // 開始等待前就存在的 error indication 不視為錯誤，但不算到達
+        set_slaves(&botnana, &[0x18, 0x04]);
+        assert_eq!(
+            botnana.ec_wait_state(None, AlState::Op, Duration::from_millis(20)),
+            Err(Error::Timeout)
+        );
+
+        // 等待中才出現的 error indication
+        set_slaves(&botnana, &[0x04, 0x04]);
+        let other = botnana.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(20));
+            set_slaves(&other, &[0x04, 0x14]);
+        });
+        assert_eq!(
+            botnana.ec_wait_state(None, AlState::Op, Duration::from_secs(5)),
+            Err(Error::AlStateError(2))
+        );
+        handle.join().unwrap();
     }
 }
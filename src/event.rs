@@ -0,0 +1,236 @@
+use botnana::Botnana;
+use history::Signal;
+use protocol::{tag_matches, Record};
+use std::{
+    mem,
+    sync::{mpsc, Arc, Mutex},
+};
+
+/// Tag Event
+/// 由 server 送來的 `tag|value` 解析而成
+#[derive(Debug, Clone, PartialEq)]
+pub struct TagEvent {
+    /// tag 名稱 (例如 `real_position`)
+    pub name: String,
+    /// slave position
+    pub position: u32,
+    /// channel
+    pub channel: u32,
+    /// tag 的值
+    pub value: String,
+}
+
+impl<'a, 'b> From<&'b Record<'a>> for TagEvent {
+    fn from(record: &'b Record<'a>) -> TagEvent {
+        TagEvent {
+            name: record.name.to_owned(),
+            position: record.position() as u32,
+            channel: record.channel() as u32,
+            value: record.value.to_owned(),
+        }
+    }
+}
+
+/// Drive Change
+/// data pool 中驅動器訊號的值改變時產生
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DriveChange {
+    /// slave position
+    pub position: u32,
+    /// channel
+    pub channel: u32,
+    /// 改變的訊號
+    pub signal: Signal,
+    /// 原本的值
+    pub old: i64,
+    /// 新的值
+    pub new: i64,
+}
+
+impl DriveChange {
+    /// 由 0 變成 1 的 bits (例如 digital inputs, status word)
+    pub fn rising_bits(&self) -> i64 {
+        !self.old & self.new
+    }
+
+    /// 由 1 變成 0 的 bits
+    pub fn falling_bits(&self) -> i64 {
+        self.old & !self.new
+    }
+
+    /// 第 `bit` 個 bit 是否由 0 變成 1，超過 63 時為 false
+    pub fn is_rising(&self, bit: u32) -> bool {
+        bit < 64 && (self.rising_bits() >> bit) & 1 != 0
+    }
+
+    /// 第 `bit` 個 bit 是否由 1 變成 0，超過 63 時為 false
+    pub fn is_falling(&self, bit: u32) -> bool {
+        bit < 64 && (self.falling_bits() >> bit) & 1 != 0
+    }
+}
+
+/// DriveChange 的 callback 函式
+type ChangeCallback = Box<dyn Fn(&DriveChange) + Send>;
+
+/// DriveChange 的 callback
+pub(crate) struct ChangeHandler {
+    pub(crate) id: SubscriptionId,
+    /// 只處理此訊號，None 表示所有訊號
+    signal: Option<Signal>,
+    /// 處理訊息時先複製出來，解鎖 handlers 後才執行
+    callback: Arc<Mutex<ChangeCallback>>,
+}
+
+/// Subscription Id
+/// 設定 callback 時取得，用來移除 callback
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(pub(crate) u64);
+
+impl SubscriptionId {
+    /// 不屬於任何 tag 的 callback (on_open, on_error 等)
+    pub(crate) const NONE: SubscriptionId = SubscriptionId(0);
+}
+
+/// 訂閱 tag event 的接收端
+pub(crate) struct Subscriber {
+    pattern: String,
+    sender: mpsc::Sender<TagEvent>,
+}
+
+impl Subscriber {
+    /// `pattern` 可以是 tag 名稱 (例如 `real_position`) 或是 tag 的 glob pattern (例如 `real_position.*.3`)
+    fn matches(&self, tag: &str, name: &str) -> bool {
+        self.pattern == name || tag_matches(&self.pattern, tag)
+    }
+}
+
+impl Botnana {
+    /// Subscribe tag events
+    /// `tag_pattern` 可以是 tag 名稱 (例如 `real_position`) 或是 tag 的 glob pattern
+    /// (例如 `real_position.1.2`, `real_position.*.3`, `*.2.1`)
+    /// 收到的事件會送到回傳的 Receiver，Receiver 被 drop 後自動取消訂閱
+    pub fn subscribe(&mut self, tag_pattern: &str) -> mpsc::Receiver<TagEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers
+            .lock()
+            .expect("subscribe")
+            .push(Subscriber {
+                pattern: tag_pattern.to_owned(),
+                sender,
+            });
+        receiver
+    }
+
+    /// Set callback for drive changes
+    /// `signal` 只處理此訊號的改變，None 表示所有訊號 (需要先啟動自動取得資料的功能)
+    /// `cb` 收到改變的驅動器、訊號與新舊值，在處理訊息的 thread 執行
+    /// 回傳的 id 可以用 `remove_callback` 移除此 callback
+    pub fn on_drive_change<F>(&mut self, signal: Option<Signal>, cb: F) -> SubscriptionId
+    where
+        F: Fn(&DriveChange) + Send + 'static,
+    {
+        let id = self.new_subscription_id();
+        self.change_handlers
+            .lock()
+            .expect("on_drive_change")
+            .push(ChangeHandler {
+                id,
+                signal,
+                callback: Arc::new(Mutex::new(Box::new(cb))),
+            });
+        id
+    }
+
+    /// 將這次訊息產生的 DriveChange 送給 callbacks
+    pub(crate) fn publish_drive_changes(&self) {
+        let changes = mem::take(&mut self.data_pool.lock().expect("publish_drive_changes").changes);
+        if changes.is_empty() {
+            return;
+        }
+        // 解鎖 handlers 後才執行 callbacks，callback 內可以呼叫 remove_callback
+        let handlers: Vec<_> = self
+            .change_handlers
+            .lock()
+            .expect("publish_drive_changes")
+            .iter()
+            .map(|handler| (handler.signal, handler.callback.clone()))
+            .collect();
+        for change in &changes {
+            for &(signal, ref callback) in handlers.iter() {
+                if signal.is_none() || signal == Some(change.signal) {
+                    (callback.lock().expect("publish_drive_changes"))(change);
+                }
+            }
+        }
+    }
+
+    /// 將 tag event 送給符合的訂閱者
+    pub(crate) fn publish_tag_event(&self, record: &Record) {
+        let mut subscribers = self.subscribers.lock().expect("publish_tag_event");
+        // 使用 rev() 是為了 remove，從後面刪除才不會影響 i 對應 vec 內的成員
+        for i in (0..subscribers.len()).rev() {
+            if subscribers[i].matches(record.tag, record.name) {
+                // Receiver 已經被 drop
+                if subscribers[i].sender.send(TagEvent::from(record)).is_err() {
+                    subscribers.remove(i);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(old: i64, new: i64) -> DriveChange {
+        DriveChange {
+            position: 1,
+            channel: 1,
+            signal: Signal::DigitalInputs,
+            old,
+            new,
+        }
+    }
+
+    #[test]
+    fn rising_and_falling_bits() {
+        let change = change(0b0110, 0b1100);
+        assert_eq!(change.rising_bits(), 0b1000);
+        assert_eq!(change.falling_bits(), 0b0010);
+        assert!(change.is_rising(3));
+        assert!(!change.is_rising(2));
+        assert!(change.is_falling(1));
+        assert!(!change.is_falling(3));
+    }
+
+    #[test]
+    fn bit_out_of_range() {
+        let change = change(0, -1);
+        assert!(change.is_rising(63));
+        assert!(!change.is_rising(64));
+        assert!(!change.is_falling(64));
+        assert!(!change.is_rising(u32::MAX));
+    }
+
+    #[test]
+    fn remove_change_callback_inside_callback() {
+        let mut botnana = Botnana::new();
+        let id = Arc::new(Mutex::new(None));
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let handle = botnana.clone();
+        let own_id = id.clone();
+        let sink = calls.clone();
+        *id.lock().unwrap() = Some(botnana.on_drive_change(None, move |change| {
+            let mut botnana = handle.clone();
+            let removed = botnana.remove_callback(own_id.lock().unwrap().unwrap());
+            sink.lock().unwrap().push((change.new, removed));
+        }));
+
+        botnana.data_pool.lock().unwrap().changes.push(change(0, 1));
+        botnana.publish_drive_changes();
+        botnana.data_pool.lock().unwrap().changes.push(change(1, 0));
+        botnana.publish_drive_changes();
+        assert_eq!(*calls.lock().unwrap(), vec![(1, true)]);
+    }
+}
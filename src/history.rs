@@ -0,0 +1,200 @@
+use std::{collections::VecDeque, time::Instant};
+
+/// Data pool 中驅動器的訊號 (Drive 的欄位)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Signal {
+    TargetPosition,
+    RealPosition,
+    DemandVelocity,
+    RealVelocity,
+    DemandTorque,
+    RealTorque,
+    FollowingError,
+    ErrorCode,
+    DigitalInputs,
+    ControlWord,
+    StatusWord,
+    OpMode,
+}
+
+/// 一筆歷史資料
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    /// 收到資料的時間
+    pub time: Instant,
+    /// 訊號的值
+    pub value: i64,
+}
+
+/// 訊號的歷史資料 (ring buffer)，超過深度時丟棄最舊的資料
+#[derive(Debug, Clone)]
+pub struct History {
+    depth: usize,
+    samples: VecDeque<Sample>,
+}
+
+impl History {
+    /// New
+    pub fn new(depth: usize) -> History {
+        History {
+            depth,
+            samples: VecDeque::with_capacity(depth),
+        }
+    }
+
+    /// 最多保留的資料筆數
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// 改變深度，多出來的舊資料會被丟棄
+    pub fn set_depth(&mut self, depth: usize) {
+        self.depth = depth;
+        while self.samples.len() > depth {
+            self.samples.pop_front();
+        }
+    }
+
+    /// 加入一筆資料
+    pub fn push(&mut self, time: Instant, value: i64) {
+        if self.depth == 0 {
+            return;
+        }
+        if self.samples.len() == self.depth {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(Sample { time, value });
+    }
+
+    /// 資料筆數
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// 是否沒有資料
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// 最新的一筆資料
+    pub fn latest(&self) -> Option<&Sample> {
+        self.samples.back()
+    }
+
+    /// 由舊到新的資料
+    pub fn iter(&self) -> impl Iterator<Item = &Sample> {
+        self.samples.iter()
+    }
+
+    /// 最舊與最新兩筆資料間的平均變化率 (每秒)，例如由位置計算速度
+    /// 少於兩筆資料或時間差為 0 時為 None
+    pub fn rate(&self) -> Option<f64> {
+        let (first, last) = (self.samples.front()?, self.samples.back()?);
+        let dt = last.time.duration_since(first.time).as_secs_f64();
+        if dt > 0.0 {
+            Some((last.value - first.value) as f64 / dt)
+        } else {
+            None
+        }
+    }
+
+    /// 最新的資料與多久之前相同 (訊號沒有變化的時間)，可以用來判斷停滯
+    pub fn unchanged_since(&self) -> Option<Instant> {
+        let latest = self.samples.back()?;
+        let mut since = latest.time;
+        for sample in self.samples.iter().rev() {
+            if sample.value != latest.value {
+                break;
+            }
+            since = sample.time;
+        }
+        Some(since)
+    }
+
+    /// 清除所有資料
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(start: Instant, ms: u64) -> Instant {
+        start + Duration::from_millis(ms)
+    }
+
+    fn values(history: &History) -> Vec<i64> {
+        history.iter().map(|x| x.value).collect()
+    }
+
+    #[test]
+    fn push_depth_zero() {
+        let mut history = History::new(0);
+        history.push(Instant::now(), 1);
+        assert!(history.is_empty());
+        assert_eq!(history.latest(), None);
+    }
+
+    #[test]
+    fn push_evicts_oldest() {
+        let start = Instant::now();
+        let mut history = History::new(3);
+        for i in 0..5 {
+            history.push(at(start, i * 10), i as i64);
+        }
+        assert_eq!(history.len(), 3);
+        assert_eq!(values(&history), vec![2, 3, 4]);
+        assert_eq!(history.latest().map(|x| x.time), Some(at(start, 40)));
+    }
+
+    #[test]
+    fn set_depth_drops_oldest() {
+        let start = Instant::now();
+        let mut history = History::new(4);
+        for i in 0..4 {
+            history.push(at(start, i * 10), i as i64);
+        }
+        history.set_depth(2);
+        assert_eq!(history.depth(), 2);
+        assert_eq!(values(&history), vec![2, 3]);
+        history.set_depth(3);
+        history.push(at(start, 40), 4);
+        assert_eq!(values(&history), vec![2, 3, 4]);
+        history.set_depth(0);
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn rate() {
+        let start = Instant::now();
+        let mut history = History::new(8);
+        assert_eq!(history.rate(), None);
+        history.push(start, 100);
+        assert_eq!(history.rate(), None);
+        history.push(start, 200);
+        // 時間差為 0
+        assert_eq!(history.rate(), None);
+        history.push(at(start, 500), 600);
+        assert_eq!(history.rate(), Some(1000.0));
+        history.push(at(start, 1000), 0);
+        assert_eq!(history.rate(), Some(-100.0));
+    }
+
+    #[test]
+    fn unchanged_since() {
+        let start = Instant::now();
+        let mut history = History::new(8);
+        assert_eq!(history.unchanged_since(), None);
+        history.push(start, 1);
+        assert_eq!(history.unchanged_since(), Some(start));
+        history.push(at(start, 10), 2);
+        history.push(at(start, 20), 2);
+        history.push(at(start, 30), 2);
+        assert_eq!(history.unchanged_since(), Some(at(start, 10)));
+        history.push(at(start, 40), 3);
+        assert_eq!(history.unchanged_since(), Some(at(start, 40)));
+    }
+}
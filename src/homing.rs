@@ -0,0 +1,156 @@
+use address::{DriveAddr, DriveTarget};
+use botnana::Botnana;
+use cia402::{
+    Cia402State, OperationMode, STATUS_OP_MODE_BIT_12, STATUS_OP_MODE_BIT_13,
+    STATUS_TARGET_REACHED,
+};
+use error::{Error, Result};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+/// 檢查 homing 狀態的間隔
+const HOMING_POLL_INTERVAL_MS: u64 = 10;
+
+/// 回歸原點的參數
+#[derive(Debug, Clone, Copy)]
+struct HomingProfile {
+    method: i8,
+    speed1: u32,
+    speed2: u32,
+    acceleration: u32,
+}
+
+/// 回歸原點的工作
+/// 依序設定 homing profile、切換到 HM 模式、開始回歸原點，
+/// 再依 status word 的 homing attained (bit 12) 與 homing error (bit 13) 判斷結果，
+/// 結束後切換回原本的 operation mode
+/// 需要先啟動自動取得資料的功能，且驅動器需要在 Operation Enabled
+#[derive(Debug, Clone)]
+pub struct HomingJob {
+    drive: DriveTarget,
+    profile: Option<HomingProfile>,
+    restore_mode: Option<OperationMode>,
+    timeout: Duration,
+}
+
+impl HomingJob {
+    /// New
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn new<A: Into<DriveTarget>>(drive: A) -> HomingJob {
+        HomingJob {
+            drive: drive.into(),
+            profile: None,
+            restore_mode: None,
+            timeout: Duration::from_secs(60),
+        }
+    }
+
+    /// 設定 homing profile，沒有設定時使用驅動器目前的設定
+    /// @method       : 回歸原點的方法 （支援的方法會因驅動器而異）
+    /// @speed1       : 搜尋 switch 的速度 ([pulse/s]，但會因驅動器而異)
+    /// @speed2       : 搜尋 index pulse 的速度 ([pulse/s]，但會因驅動器而異)
+    /// @acceleration : 加速度 ([pulse/s^2]，但會因驅動器而異)
+    pub fn profile(mut self, method: i8, speed1: u32, speed2: u32, acceleration: u32) -> Self {
+        self.profile = Some(HomingProfile {
+            method,
+            speed1,
+            speed2,
+            acceleration,
+        });
+        self
+    }
+
+    /// 結束後要切換回的 operation mode
+    /// 沒有設定時切換回開始前由 data pool 讀到的 mode，讀不到時維持在 HM 模式
+    pub fn restore_mode(mut self, mode: OperationMode) -> Self {
+        self.restore_mode = Some(mode);
+        self
+    }
+
+    /// 等待回歸原點完成的時間 (預設 60 秒)
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 執行回歸原點並等待結果
+    /// 不可在 callback 內呼叫，data pool 由處理 callback 的 thread 更新
+    pub fn run(&self, botnana: &mut Botnana) -> Result<()> {
+        let addr = botnana.resolve_drive(self.drive.clone())?;
+        let state = botnana.drive_state(addr).ok_or(Error::DriveNotFound)?;
+        if state != Cia402State::OperationEnabled {
+            return Err(Error::DriveState(state));
+        }
+
+        let restore_mode = self
+            .restore_mode
+            .or_else(|| botnana.drive_operation_mode(addr))
+            .filter(|x| *x != OperationMode::Homing);
+        if let Some(p) = self.profile {
+            botnana.try_set_drive_homing_profile(
+                addr,
+                p.method,
+                p.speed1,
+                p.speed2,
+                p.acceleration,
+            )?;
+        }
+        botnana.try_set_drive_mode(addr, OperationMode::Homing)?;
+        botnana.try_drive_homing_start(addr)?;
+
+        let result = self.wait(botnana, &addr);
+        match restore_mode {
+            Some(mode) => result.and(botnana.try_set_drive_mode(addr, mode)),
+            None => result,
+        }
+    }
+
+    /// 在另一個 thread 執行回歸原點，結果由 `cb` 取得
+    /// 無法建立 thread 時回傳 `Error::Io`，`cb` 不會被呼叫
+    pub fn spawn<F>(self, botnana: &Botnana, cb: F) -> Result<thread::JoinHandle<()>>
+    where
+        F: FnOnce(Result<()>) + Send + 'static,
+    {
+        let mut botnana = botnana.clone();
+        thread::Builder::new()
+            .name("HOMING".to_string())
+            .spawn(move || cb(self.run(&mut botnana)))
+            .map_err(|e| Error::Io(e.to_string()))
+    }
+
+    /// 等待 homing attained 或 homing error
+    /// 切換前 status word 的 bit 12, 13 屬於原本的 mode，要等 operation mode 讀回 HM 才判斷，
+    /// 開始前 homing attained 可能還是上一次的結果，所以要先看到 bit 12 清除才算開始
+    fn wait(&self, botnana: &Botnana, addr: &DriveAddr) -> Result<()> {
+        let deadline = Instant::now() + self.timeout;
+        let mut started = false;
+        loop {
+            let (status_word, op_mode) = botnana
+                .snapshot()
+                .drive(addr)
+                .map(|x| (x.status_word, x.operation_mode()))
+                .ok_or(Error::DriveNotFound)?;
+            let state = Cia402State::from_status_word(status_word);
+            if state != Cia402State::OperationEnabled {
+                return Err(Error::DriveState(state));
+            }
+            if op_mode == Some(OperationMode::Homing) {
+                if status_word & STATUS_OP_MODE_BIT_13 != 0 {
+                    return Err(Error::HomingFailed);
+                }
+                let attained = status_word & STATUS_OP_MODE_BIT_12 != 0
+                    && status_word & STATUS_TARGET_REACHED != 0;
+                if started && attained {
+                    return Ok(());
+                }
+                started = started || !attained;
+            }
+            if Instant::now() >= deadline {
+                return Err(Error::Timeout);
+            }
+            thread::sleep(Duration::from_millis(HOMING_POLL_INTERVAL_MS));
+        }
+    }
+}
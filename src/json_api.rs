@@ -1,30 +1,299 @@
 extern crate libc;
+use address::DriveTarget;
 use botnana::Botnana;
-use std::str;
+use error::{Error, Result};
+use protocol::Record;
+use serde_json::{self, Value};
+use std::{
+    collections::HashMap,
+    str,
+    sync::{
+        mpsc::{self, RecvTimeoutError, TryRecvError},
+        Arc, Mutex,
+    },
+    task::Waker,
+    time::Duration,
+};
+
+/// 等待回應中的 JSON-RPC request
+pub(crate) struct PendingRequest {
+    sender: mpsc::Sender<Result<Value>>,
+    /// 非同步等待時，收到回應後要喚醒的 task
+    waker: Option<Waker>,
+}
+
+/// 等待回應中的 JSON-RPC requests (id -> 回傳通道)
+pub(crate) type PendingRequests = HashMap<u64, PendingRequest>;
+
+/// JSON-RPC Reply
+/// 送出 request 後取得的 handle，用來等待或查詢該次呼叫的回應
+pub struct Reply {
+    id: u64,
+    receiver: mpsc::Receiver<Result<Value>>,
+    pending: Arc<Mutex<PendingRequests>>,
+}
+
+impl Reply {
+    /// Request id
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// 等待回應，回傳 `result` 或是對應的錯誤
+    /// 不可在 callback 內呼叫，callback 與回應處理在同一個 thread
+    pub fn wait(self, timeout: Duration) -> Result<Value> {
+        match self.receiver.recv_timeout(timeout) {
+            Ok(x) => x,
+            Err(RecvTimeoutError::Timeout) => Err(Error::Timeout),
+            Err(RecvTimeoutError::Disconnected) => Err(Error::NotConnected),
+        }
+    }
+
+    /// 查詢是否已收到回應，不會等待
+    pub fn try_get(&self) -> Option<Result<Value>> {
+        match self.receiver.try_recv() {
+            Ok(x) => Some(x),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(Error::NotConnected)),
+        }
+    }
+
+    /// 設定收到回應時要喚醒的 task
+    #[cfg(feature = "async")]
+    pub(crate) fn set_waker(&self, waker: &Waker) {
+        if let Some(request) = self.pending.lock().expect("set_waker").get_mut(&self.id) {
+            request.waker = Some(waker.clone());
+        }
+    }
+}
+
+impl Drop for Reply {
+    fn drop(&mut self) {
+        // 不再等待回應，從等待表中移除
+        self.pending.lock().expect("Reply::drop").remove(&self.id);
+    }
+}
+
+/// tag 中的 request id (例如 `error.12` 中的 12)
+fn record_id(record: &Record) -> Option<u64> {
+    let mut fields = record.tag.splitn(2, '.');
+    fields.next();
+    fields.next().and_then(|x| x.parse::<u64>().ok())
+}
+
+/// 開始執行時輸出 `evaluating|id`，之後沒有 id 的 error (例如編譯失敗) 屬於這個 request
+fn evaluation_start(id: u64) -> String {
+    format!("10 emit .( evaluating|{}) 10 emit ", id)
+}
 
 impl Botnana {
+    /// Send JSON-RPC request
+    /// 加上 id 後送出，並登記到等待表中
+    pub fn send_request(&mut self, msg: &str) -> Reply {
+        let (id, reply) = self.new_reply();
+        let result = match serde_json::from_str::<Value>(msg) {
+            Ok(Value::Object(mut request)) => {
+                request.insert("id".to_owned(), Value::from(id));
+                self.try_send_message(&Value::Object(request).to_string())
+            }
+            _ => Err(Error::Parse(format!("Invalid JSON-RPC request: {}", msg))),
+        };
+        if let Err(e) = result {
+            self.resolve_request(id, Err(e));
+        }
+        reply
+    }
+
+    /// Evaluate
+    /// 與 `evaluate` 相同，但回傳的 Reply 會在 script 執行完畢後收到 `Ok(Value::Null)`，
+    /// 若執行過程中 abort 或編譯失敗則收到 `Error::ForthAbort` (內容為錯誤訊息)
+    pub fn try_evaluate(&mut self, script: &str) -> Result<Reply> {
+        let (id, reply) = self.new_reply();
+        let script = format!(
+            "{start}{script}\n 10 emit .( evaluated|{id}) 10 emit",
+            start = evaluation_start(id),
+            script = script,
+            id = id,
+        );
+        self.send_evaluate(&script).map(|_| reply)
+    }
+
+    /// 與 `try_evaluate` 相同，但 script 由 request id 產生
+    /// script 會被包在 `:noname ... ;` 中以 `catch` 執行，所以不可包含定義 (例如 `:`)，
+    /// abort 時 Reply 收到 `catch` 取得的錯誤碼
+    /// script 可以輸出 `script_result.{id}|value` 回傳結果，Reply 會收到 `Ok(Value::String(value))`
+    pub(crate) fn try_evaluate_with<F>(&mut self, f: F) -> Result<Reply>
+    where
+        F: FnOnce(u64) -> String,
+    {
+        let (id, reply) = self.new_reply();
+        // abort 由 catch 取得並以 `error.{id}|code` 回報，不會與其他 script 的 abort 混淆
+        let script = format!(
+            "{start}:noname {script}\n ; catch dup 0= [if] drop 10 emit .( evaluated|{id}) 10 emit \
+             [else] 10 emit .( error.{id}|) . 10 emit [then]",
+            start = evaluation_start(id),
+            script = f(id),
+            id = id,
+        );
+        self.send_evaluate(&script).map(|_| reply)
+    }
+
+    /// 配置新的 request id 並登記到等待表中
+    fn new_reply(&self) -> (u64, Reply) {
+        let id = {
+            let mut next_id = self.next_request_id.lock().expect("new_reply");
+            *next_id += 1;
+            *next_id
+        };
+        let (sender, receiver) = mpsc::channel();
+        self.pending_requests
+            .lock()
+            .expect("new_reply")
+            .insert(
+                id,
+                PendingRequest {
+                    sender,
+                    waker: None,
+                },
+            );
+        let reply = Reply {
+            id,
+            receiver,
+            pending: self.pending_requests.clone(),
+        };
+        (id, reply)
+    }
+
+    /// 已經收到錯誤的 Reply
+    fn failed_reply(&self, err: Error) -> Reply {
+        let (id, reply) = self.new_reply();
+        self.resolve_request(id, Err(err));
+        reply
+    }
+
+    /// 將結果交給等待中的 Reply
+    fn resolve_request(&self, id: u64, result: Result<Value>) {
+        let request = self
+            .pending_requests
+            .lock()
+            .expect("resolve_request")
+            .remove(&id);
+        if let Some(request) = request {
+            let _ = request.sender.send(result);
+            if let Some(waker) = request.waker {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Request 已結束，不再是正在執行的 `try_evaluate`
+    fn finish_evaluation(&self, id: u64) {
+        let mut evaluating = self.evaluating.lock().expect("finish_evaluation");
+        if *evaluating == Some(id) {
+            *evaluating = None;
+        }
+    }
+
+    /// 連線中斷，取消所有等待中的 requests，等待中的 Reply 會收到 `Error::NotConnected`
+    pub(crate) fn cancel_requests(&self) {
+        *self.evaluating.lock().expect("cancel_requests") = None;
+        let requests: Vec<PendingRequest> = self
+            .pending_requests
+            .lock()
+            .expect("cancel_requests")
+            .drain()
+            .map(|(_, x)| x)
+            .collect();
+        for request in requests {
+            drop(request.sender);
+            if let Some(waker) = request.waker {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Handle evaluation events
+    /// `evaluating|id` 表示 script 開始執行，`evaluated|id` 表示 script 已執行完畢，
+    /// `error.id|code` 表示 script 被中止
+    /// `script_result.id|value` 是 script 回傳的結果
+    pub(crate) fn handle_evaluation_event(&self, record: &Record) {
+        let value = record.value;
+        match record.name {
+            "evaluating" => {
+                if let Ok(id) = value.trim().parse::<u64>() {
+                    *self.evaluating.lock().expect("handle_evaluation_event") = Some(id);
+                }
+            }
+            "evaluated" => {
+                if let Ok(id) = value.trim().parse::<u64>() {
+                    self.finish_evaluation(id);
+                    self.resolve_request(id, Ok(Value::Null));
+                }
+            }
+            "error" => {
+                // 沒有 id 的 error 屬於正在執行的 `try_evaluate`，
+                // 其他情況 (例如 `evaluate`、script buffer 或 program 被中止) 與 Reply 無關
+                let id = match record_id(record) {
+                    Some(id) => {
+                        self.finish_evaluation(id);
+                        Some(id)
+                    }
+                    None => self.evaluating.lock().expect("handle_evaluation_event").take(),
+                };
+                if let Some(id) = id {
+                    self.resolve_request(id, Err(Error::ForthAbort(value.trim().to_owned())));
+                }
+            }
+            "script_result" => {
+                if let Some(id) = record_id(record) {
+                    self.resolve_request(id, Ok(Value::from(value.trim())));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Handle JSON-RPC response
+    /// 如果訊息是等待中的 request 的回應就交給對應的 Reply，回傳 true
+    pub(crate) fn handle_response(&mut self, message: &str) -> bool {
+        if !message.trim_start().starts_with('{') {
+            return false;
+        }
+        if let Ok(Value::Object(mut response)) = serde_json::from_str::<Value>(message) {
+            if let Some(id) = response.get("id").and_then(|x| x.as_u64()) {
+                let result = match response.remove("error") {
+                    Some(error) => Err(Error::from_rpc_error(&error)),
+                    None => Ok(response.remove("result").unwrap_or(Value::Null)),
+                };
+                self.resolve_request(id, result);
+                return true;
+            }
+        }
+        false
+    }
+
     /// motion.pool
-    pub fn motion_poll(&mut self) {
+    pub fn motion_poll(&mut self) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"motion.poll"}"#;
-        self.send_message(msg);
+        self.send_request(msg)
     }
 
     /// profiler.restart
-    pub fn profiler_restart(&mut self) {
+    pub fn profiler_restart(&mut self) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"profiler.restart"}"#;
-        self.send_message(msg);
+        self.send_request(msg)
     }
 
     /// profiler.output
-    pub fn profiler_output(&mut self) {
+    pub fn profiler_output(&mut self) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"profiler.output"}"#;
-        self.send_message(msg);
+        self.send_request(msg)
     }
 
     /// version.get
-    pub fn version_get(&mut self) {
+    pub fn version_get(&mut self) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"version.get"}"#;
-        self.send_message(msg);
+        self.send_request(msg)
     }
 
     /// config.slave.set
@@ -35,7 +304,7 @@ impl Botnana {
         channel: u32,
         param: &str,
         value: i32,
-    ) {
+    ) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"config.slave.set","params":{"alias":"#.to_owned()
             + alias.to_string().as_str()
             + r#","position":"#
@@ -47,10 +316,33 @@ impl Botnana {
             + r#"":"#
             + value.to_string().as_str()
             + r#"}}"#;
-        self.send_message(&msg);
+        self.send_request(&msg)
+    }
+
+    /// config.slave.set
+    /// @drive    : 驅動器位址或 axis 名稱
+    fn config_drive_set<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        param: &str,
+        value: i32,
+    ) -> Reply {
+        let addr = match self.resolve_drive(drive) {
+            Ok(x) => x,
+            Err(e) => return self.failed_reply(e),
+        };
+        let (alias, position) = addr.slave.alias_and_position();
+        self.config_slave_set(
+            u32::from(alias),
+            u32::from(position),
+            u32::from(addr.channel),
+            param,
+            value,
+        )
     }
 
     /// config.slave.set (homing_method)
+    #[deprecated(note = "use config_drive_set_homing_method")]
     pub fn config_slave_set_homing_method(
         &mut self,
         alias: u32,
@@ -61,7 +353,18 @@ impl Botnana {
         self.config_slave_set(alias, position, channel, "homing_method", value);
     }
 
+    /// config.slave.set (homing_method)
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn config_drive_set_homing_method<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        value: i32,
+    ) -> Reply {
+        self.config_drive_set(drive, "homing_method", value)
+    }
+
     /// config.slave.set (homing_speed_1)
+    #[deprecated(note = "use config_drive_set_homing_speed_1")]
     pub fn config_slave_set_homing_speed_1(
         &mut self,
         alias: u32,
@@ -72,7 +375,18 @@ impl Botnana {
         self.config_slave_set(alias, position, channel, "homing_speed_1", value);
     }
 
+    /// config.slave.set (homing_speed_1)
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn config_drive_set_homing_speed_1<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        value: i32,
+    ) -> Reply {
+        self.config_drive_set(drive, "homing_speed_1", value)
+    }
+
     /// config.slave.set (homing_speed_2)
+    #[deprecated(note = "use config_drive_set_homing_speed_2")]
     pub fn config_slave_set_homing_speed_2(
         &mut self,
         alias: u32,
@@ -83,7 +397,18 @@ impl Botnana {
         self.config_slave_set(alias, position, channel, "homing_speed_2", value);
     }
 
+    /// config.slave.set (homing_speed_2)
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn config_drive_set_homing_speed_2<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        value: i32,
+    ) -> Reply {
+        self.config_drive_set(drive, "homing_speed_2", value)
+    }
+
     /// config.slave.set (homing_acceleration)
+    #[deprecated(note = "use config_drive_set_homing_acceleration")]
     pub fn config_slave_set_homing_acceleration(
         &mut self,
         alias: u32,
@@ -94,7 +419,18 @@ impl Botnana {
         self.config_slave_set(alias, position, channel, "homing_acceleration", value);
     }
 
+    /// config.slave.set (homing_acceleration)
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn config_drive_set_homing_acceleration<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        value: i32,
+    ) -> Reply {
+        self.config_drive_set(drive, "homing_acceleration", value)
+    }
+
     /// config.slave.set (profile_velocity)
+    #[deprecated(note = "use config_drive_set_profile_velocity")]
     pub fn config_slave_set_profile_velocity(
         &mut self,
         alias: u32,
@@ -105,7 +441,18 @@ impl Botnana {
         self.config_slave_set(alias, position, channel, "profile_velocity", value);
     }
 
+    /// config.slave.set (profile_velocity)
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn config_drive_set_profile_velocity<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        value: i32,
+    ) -> Reply {
+        self.config_drive_set(drive, "profile_velocity", value)
+    }
+
     /// config.slave.set (profile_acceleration)
+    #[deprecated(note = "use config_drive_set_profile_acceleration")]
     pub fn config_slave_set_profile_acceleration(
         &mut self,
         alias: u32,
@@ -116,7 +463,18 @@ impl Botnana {
         self.config_slave_set(alias, position, channel, "profile_acceleration", value);
     }
 
+    /// config.slave.set (profile_acceleration)
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn config_drive_set_profile_acceleration<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        value: i32,
+    ) -> Reply {
+        self.config_drive_set(drive, "profile_acceleration", value)
+    }
+
     /// config.slave.set (profile_deceleration)
+    #[deprecated(note = "use config_drive_set_profile_deceleration")]
     pub fn config_slave_set_profile_deceleration(
         &mut self,
         alias: u32,
@@ -127,7 +485,18 @@ impl Botnana {
         self.config_slave_set(alias, position, channel, "profile_deceleration", value);
     }
 
+    /// config.slave.set (profile_deceleration)
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn config_drive_set_profile_deceleration<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        value: i32,
+    ) -> Reply {
+        self.config_drive_set(drive, "profile_deceleration", value)
+    }
+
     /// config.slave.set (pdo_velocity_offset)
+    #[deprecated(note = "use config_drive_set_pdo_velocity_offset")]
     pub fn config_slave_set_pdo_velocity_offset(
         &mut self,
         alias: u32,
@@ -138,7 +507,18 @@ impl Botnana {
         self.config_slave_set(alias, position, channel, "pdo_velocity_offset", value);
     }
 
+    /// config.slave.set (pdo_velocity_offset)
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn config_drive_set_pdo_velocity_offset<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        value: i32,
+    ) -> Reply {
+        self.config_drive_set(drive, "pdo_velocity_offset", value)
+    }
+
     /// config.slave.set (pdo_torque_offset)
+    #[deprecated(note = "use config_drive_set_pdo_torque_offset")]
     pub fn config_slave_set_pdo_torque_offset(
         &mut self,
         alias: u32,
@@ -149,7 +529,18 @@ impl Botnana {
         self.config_slave_set(alias, position, channel, "pdo_torque_offset", value);
     }
 
+    /// config.slave.set (pdo_torque_offset)
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn config_drive_set_pdo_torque_offset<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        value: i32,
+    ) -> Reply {
+        self.config_drive_set(drive, "pdo_torque_offset", value)
+    }
+
     /// config.slave.set (pdo_digital_inputs)
+    #[deprecated(note = "use config_drive_set_pdo_digital_inputs")]
     pub fn config_slave_set_pdo_digital_inputs(
         &mut self,
         alias: u32,
@@ -160,7 +551,18 @@ impl Botnana {
         self.config_slave_set(alias, position, channel, "pdo_digital_inputs", value);
     }
 
+    /// config.slave.set (pdo_digital_inputs)
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn config_drive_set_pdo_digital_inputs<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        value: i32,
+    ) -> Reply {
+        self.config_drive_set(drive, "pdo_digital_inputs", value)
+    }
+
     /// config.slave.set (pdo_demand_position)
+    #[deprecated(note = "use config_drive_set_pdo_demand_position")]
     pub fn config_slave_set_pdo_demand_position(
         &mut self,
         alias: u32,
@@ -171,7 +573,18 @@ impl Botnana {
         self.config_slave_set(alias, position, channel, "pdo_demand_position", value);
     }
 
+    /// config.slave.set (pdo_demand_position)
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn config_drive_set_pdo_demand_position<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        value: i32,
+    ) -> Reply {
+        self.config_drive_set(drive, "pdo_demand_position", value)
+    }
+
     /// config.slave.set (pdo_demand_velocity)
+    #[deprecated(note = "use config_drive_set_pdo_demand_velocity")]
     pub fn config_slave_set_pdo_demand_velocity(
         &mut self,
         alias: u32,
@@ -182,7 +595,18 @@ impl Botnana {
         self.config_slave_set(alias, position, channel, "pdo_demand_velocity", value);
     }
 
+    /// config.slave.set (pdo_demand_velocity)
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn config_drive_set_pdo_demand_velocity<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        value: i32,
+    ) -> Reply {
+        self.config_drive_set(drive, "pdo_demand_velocity", value)
+    }
+
     /// config.slave.set (pdo_demand_torque)
+    #[deprecated(note = "use config_drive_set_pdo_demand_torque")]
     pub fn config_slave_set_pdo_demand_torque(
         &mut self,
         alias: u32,
@@ -193,7 +617,18 @@ impl Botnana {
         self.config_slave_set(alias, position, channel, "pdo_demand_torque", value);
     }
 
+    /// config.slave.set (pdo_demand_torque)
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn config_drive_set_pdo_demand_torque<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        value: i32,
+    ) -> Reply {
+        self.config_drive_set(drive, "pdo_demand_torque", value)
+    }
+
     /// config.slave.set (pdo_real_velocity)
+    #[deprecated(note = "use config_drive_set_pdo_real_velocity")]
     pub fn config_slave_set_pdo_real_velocity(
         &mut self,
         alias: u32,
@@ -204,7 +639,18 @@ impl Botnana {
         self.config_slave_set(alias, position, channel, "pdo_real_velocity", value);
     }
 
+    /// config.slave.set (pdo_real_velocity)
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn config_drive_set_pdo_real_velocity<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        value: i32,
+    ) -> Reply {
+        self.config_drive_set(drive, "pdo_real_velocity", value)
+    }
+
     /// config.slave.set (pdo_real_torque)
+    #[deprecated(note = "use config_drive_set_pdo_real_torque")]
     pub fn config_slave_set_pdo_real_torque(
         &mut self,
         alias: u32,
@@ -215,8 +661,18 @@ impl Botnana {
         self.config_slave_set(alias, position, channel, "pdo_real_torque", value);
     }
 
+    /// config.slave.set (pdo_real_torque)
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn config_drive_set_pdo_real_torque<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        value: i32,
+    ) -> Reply {
+        self.config_drive_set(drive, "pdo_real_torque", value)
+    }
+
     /// config.slave.get
-    pub fn config_slave_get(&mut self, alias: u32, position: u32, channel: u32) {
+    fn config_slave_get_reply(&mut self, alias: u32, position: u32, channel: u32) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"config.slave.get","params":{"alias":"#.to_owned()
             + alias.to_string().as_str()
             + r#","position":"#
@@ -224,42 +680,63 @@ impl Botnana {
             + r#","channel":"#
             + channel.to_string().as_str()
             + r#"}}"#;
-        self.send_message(&msg);
+        self.send_request(&msg)
+    }
+
+    /// config.slave.get
+    #[deprecated(note = "use config_drive_get")]
+    pub fn config_slave_get(&mut self, alias: u32, position: u32, channel: u32) {
+        self.config_slave_get_reply(alias, position, channel);
+    }
+
+    /// config.slave.get
+    /// @drive    : 驅動器位址或 axis 名稱
+    pub fn config_drive_get<A: Into<DriveTarget>>(&mut self, drive: A) -> Reply {
+        let addr = match self.resolve_drive(drive) {
+            Ok(x) => x,
+            Err(e) => return self.failed_reply(e),
+        };
+        let (alias, position) = addr.slave.alias_and_position();
+        self.config_slave_get_reply(
+            u32::from(alias),
+            u32::from(position),
+            u32::from(addr.channel),
+        )
     }
 
     /// config.motion.set
-    fn config_motion_set(&mut self, param: &str, value: u32) {
+    fn config_motion_set(&mut self, param: &str, value: u32) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"config.motion.set","params":{""#.to_owned()
             + param
             + r#"":"#
             + value.to_string().as_str()
             + r#"}}"#;
-        self.send_message(&msg);
+        self.send_request(&msg)
     }
 
     /// config.motion.set (period_us)
-    pub fn config_motion_set_period_us(&mut self, value: u32) {
-        self.config_motion_set("period_us", value);
+    pub fn config_motion_set_period_us(&mut self, value: u32) -> Reply {
+        self.config_motion_set("period_us", value)
     }
 
     /// config.motion.set (group_capacity)
-    pub fn config_motion_set_group_capacity(&mut self, value: u32) {
-        self.config_motion_set("group_capacity", value);
+    pub fn config_motion_set_group_capacity(&mut self, value: u32) -> Reply {
+        self.config_motion_set("group_capacity", value)
     }
 
     /// config.motion.set (axis_capacity)
-    pub fn config_motion_set_axis_capacity(&mut self, value: u32) {
-        self.config_motion_set("axis_capacity", value);
+    pub fn config_motion_set_axis_capacity(&mut self, value: u32) -> Reply {
+        self.config_motion_set("axis_capacity", value)
     }
 
     /// config.motion.get
-    pub fn config_motion_get(&mut self) {
+    pub fn config_motion_get(&mut self) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"config.motion.get"}"#;
-        self.send_message(msg);
+        self.send_request(msg)
     }
 
     /// config.group.set for string data type
-    fn config_group_set_string(&mut self, position: u32, param: &str, value: &str) {
+    fn config_group_set_string(&mut self, position: u32, param: &str, value: &str) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"config.group.set","params":{"#.to_owned()
             + r#""position":"#
             + position.to_string().as_str()
@@ -268,27 +745,27 @@ impl Botnana {
             + r#"":""#
             + value
             + r#""}}"#;
-        self.send_message(&msg);
+        self.send_request(&msg)
     }
 
     /// config.group.set (name)
-    pub fn config_group_set_name(&mut self, position: u32, name: &str) {
-        self.config_group_set_string(position, "name", name);
+    pub fn config_group_set_name(&mut self, position: u32, name: &str) -> Reply {
+        self.config_group_set_string(position, "name", name)
     }
 
     /// config.group.set (gtype as 1D)
-    pub fn config_group_set_type_as_1d(&mut self, position: u32, a1: u32) {
+    pub fn config_group_set_type_as_1d(&mut self, position: u32, a1: u32) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"config.group.set","params":{"#.to_owned()
             + r#""position":"#
             + position.to_string().as_str()
             + r#","gtype":"1D", "mapping":["#
             + a1.to_string().as_str()
             + r#"]}}"#;
-        self.send_message(&msg);
+        self.send_request(&msg)
     }
 
     /// config.group.set (gtype as 2D)
-    pub fn config_group_set_type_as_2d(&mut self, position: u32, a1: u32, a2: u32) {
+    pub fn config_group_set_type_as_2d(&mut self, position: u32, a1: u32, a2: u32) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"config.group.set","params":{"#.to_owned()
             + r#""position":"#
             + position.to_string().as_str()
@@ -297,11 +774,17 @@ impl Botnana {
             + r#","#
             + a2.to_string().as_str()
             + r#"]}}"#;
-        self.send_message(&msg);
+        self.send_request(&msg)
     }
 
     /// config.group.set (gtype as 3D)
-    pub fn config_group_set_type_as_3d(&mut self, position: u32, a1: u32, a2: u32, a3: u32) {
+    pub fn config_group_set_type_as_3d(
+        &mut self,
+        position: u32,
+        a1: u32,
+        a2: u32,
+        a3: u32,
+    ) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"config.group.set","params":{"#.to_owned()
             + r#""position":"#
             + position.to_string().as_str()
@@ -312,22 +795,22 @@ impl Botnana {
             + r#","#
             + a3.to_string().as_str()
             + r#"]}}"#;
-        self.send_message(&msg);
+        self.send_request(&msg)
     }
 
     /// config.group.set (gtype as SINE)
-    pub fn config_group_set_type_as_sine(&mut self, position: u32, a1: u32) {
+    pub fn config_group_set_type_as_sine(&mut self, position: u32, a1: u32) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"config.group.set","params":{"#.to_owned()
             + r#""position":"#
             + position.to_string().as_str()
             + r#","gtype":"SINE", "mapping":["#
             + a1.to_string().as_str()
             + r#"]}}"#;
-        self.send_message(&msg);
+        self.send_request(&msg)
     }
 
     /// config.group.set for double data type
-    fn config_group_set_double(&mut self, position: u32, param: &str, value: f64) {
+    fn config_group_set_double(&mut self, position: u32, param: &str, value: f64) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"config.group.set","params":{"#.to_owned()
             + r#""position":"#
             + position.to_string().as_str()
@@ -336,35 +819,35 @@ impl Botnana {
             + r#"":"#
             + value.to_string().as_str()
             + r#"}}"#;
-        self.send_message(&msg);
+        self.send_request(&msg)
     }
 
     /// config.group.set (vmax)
-    pub fn config_group_set_vmax(&mut self, position: u32, vmax: f64) {
-        self.config_group_set_double(position, "vmax", vmax);
+    pub fn config_group_set_vmax(&mut self, position: u32, vmax: f64) -> Reply {
+        self.config_group_set_double(position, "vmax", vmax)
     }
 
     /// config.group.set (amax)
-    pub fn config_group_set_amax(&mut self, position: u32, amax: f64) {
-        self.config_group_set_double(position, "amax", amax);
+    pub fn config_group_set_amax(&mut self, position: u32, amax: f64) -> Reply {
+        self.config_group_set_double(position, "amax", amax)
     }
 
     /// config.group.set (jmax)
-    pub fn config_group_set_jmax(&mut self, position: u32, jmax: f64) {
-        self.config_group_set_double(position, "jmax", jmax);
+    pub fn config_group_set_jmax(&mut self, position: u32, jmax: f64) -> Reply {
+        self.config_group_set_double(position, "jmax", jmax)
     }
 
     /// config.group.get
-    pub fn config_group_get(&mut self, position: u32) {
+    pub fn config_group_get(&mut self, position: u32) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"config.group.get","params":{"position":"#
             .to_owned()
             + position.to_string().as_str()
             + r#"}}"#;
-        self.send_message(&msg);
+        self.send_request(&msg)
     }
 
     /// config.axis.set for string data type
-    fn config_axis_set_string(&mut self, position: u32, param: &str, value: &str) {
+    fn config_axis_set_string(&mut self, position: u32, param: &str, value: &str) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"config.axis.set","params":{"#.to_owned()
             + r#""position":"#
             + position.to_string().as_str()
@@ -373,12 +856,12 @@ impl Botnana {
             + r#"":""#
             + value
             + r#""}}"#;
-        self.send_message(&msg.to_owned());
+        self.send_request(&msg)
     }
 
     /// config.axis.set (name)
-    pub fn config_axis_set_name(&mut self, position: u32, name: &str) {
-        self.config_axis_set_string(position, "name", name);
+    pub fn config_axis_set_name(&mut self, position: u32, name: &str) -> Reply {
+        self.config_axis_set_string(position, "name", name)
     }
 
     /// config.axis.set (encoder_length_unit)
@@ -397,7 +880,7 @@ impl Botnana {
     }
 
     /// config.axis.set for double data type
-    fn config_axis_set_double(&mut self, position: u32, param: &str, value: f64) {
+    fn config_axis_set_double(&mut self, position: u32, param: &str, value: f64) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"config.axis.set","params":{"#.to_owned()
             + r#""position":"#
             + position.to_string().as_str()
@@ -406,46 +889,46 @@ impl Botnana {
             + r#"":"#
             + value.to_string().as_str()
             + r#"}}"#;
-        self.send_message(&msg);
+        self.send_request(&msg)
     }
 
     /// config.group.set (home_offset)
-    pub fn config_axis_set_home_offset(&mut self, position: u32, offset: f64) {
-        self.config_axis_set_double(position, "home_offset", offset);
+    pub fn config_axis_set_home_offset(&mut self, position: u32, offset: f64) -> Reply {
+        self.config_axis_set_double(position, "home_offset", offset)
     }
 
     /// config.group.set (encoder_ppu)
-    pub fn config_axis_set_encoder_ppu(&mut self, position: u32, value: f64) {
-        self.config_axis_set_double(position, "encoder_ppu", value);
+    pub fn config_axis_set_encoder_ppu(&mut self, position: u32, value: f64) -> Reply {
+        self.config_axis_set_double(position, "encoder_ppu", value)
     }
 
     /// config.group.set (ext_encoder_ppu)
-    pub fn config_axis_set_ext_encoder_ppu(&mut self, position: u32, value: f64) {
-        self.config_axis_set_double(position, "ext_encoder_ppu", value);
+    pub fn config_axis_set_ext_encoder_ppu(&mut self, position: u32, value: f64) -> Reply {
+        self.config_axis_set_double(position, "ext_encoder_ppu", value)
     }
 
     /// config.group.set (closed_loop_filter)
-    pub fn config_axis_set_closed_loop_filter(&mut self, position: u32, value: f64) {
-        self.config_axis_set_double(position, "closed_loop_filter", value);
+    pub fn config_axis_set_closed_loop_filter(&mut self, position: u32, value: f64) -> Reply {
+        self.config_axis_set_double(position, "closed_loop_filter", value)
     }
 
     /// config.group.set (max_position_deviation)
-    pub fn config_axis_set_max_position_deviation(&mut self, position: u32, value: f64) {
-        self.config_axis_set_double(position, "max_position_deviation", value);
+    pub fn config_axis_set_max_position_deviation(&mut self, position: u32, value: f64) -> Reply {
+        self.config_axis_set_double(position, "max_position_deviation", value)
     }
 
     /// config.group.set (amax)
-    pub fn config_axis_set_amax(&mut self, position: u32, value: f64) {
-        self.config_axis_set_double(position, "amax", value);
+    pub fn config_axis_set_amax(&mut self, position: u32, value: f64) -> Reply {
+        self.config_axis_set_double(position, "amax", value)
     }
 
     /// config.group.set (vmax)
-    pub fn config_axis_set_vmax(&mut self, position: u32, value: f64) {
-        self.config_axis_set_double(position, "vmax", value);
+    pub fn config_axis_set_vmax(&mut self, position: u32, value: f64) -> Reply {
+        self.config_axis_set_double(position, "vmax", value)
     }
 
     /// config.axis.set for integer data type
-    fn config_axis_set_integer(&mut self, position: u32, param: &str, value: i32) {
+    fn config_axis_set_integer(&mut self, position: u32, param: &str, value: i32) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"config.axis.set","params":{"#.to_owned()
             + r#""position":"#
             + position.to_string().as_str()
@@ -454,72 +937,175 @@ impl Botnana {
             + r#"":"#
             + value.to_string().as_str()
             + r#"}}"#;
-        self.send_message(&msg);
+        self.send_request(&msg)
     }
 
     /// config.group.set (encoder_direction)
-    pub fn config_axis_set_encoder_direction(&mut self, position: u32, value: i32) {
-        self.config_axis_set_integer(position, "encoder_direction", value);
+    pub fn config_axis_set_encoder_direction(&mut self, position: u32, value: i32) -> Reply {
+        self.config_axis_set_integer(position, "encoder_direction", value)
     }
 
     /// config.group.set (ext_encoder_direction)
-    pub fn config_axis_set_ext_encoder_direction(&mut self, position: u32, value: i32) {
-        self.config_axis_set_integer(position, "ext_encoder_direction", value);
+    pub fn config_axis_set_ext_encoder_direction(&mut self, position: u32, value: i32) -> Reply {
+        self.config_axis_set_integer(position, "ext_encoder_direction", value)
     }
 
     /// config.group.set (drive_alias)
-    pub fn config_axis_set_drive_alias(&mut self, position: u32, value: i32) {
-        self.config_axis_set_integer(position, "drive_alias", value);
+    pub fn config_axis_set_drive_alias(&mut self, position: u32, value: i32) -> Reply {
+        self.config_axis_set_integer(position, "drive_alias", value)
     }
 
     /// config.group.set (drive_slave_position)
-    pub fn config_axis_set_drive_slave_position(&mut self, position: u32, value: i32) {
-        self.config_axis_set_integer(position, "drive_slave_position", value);
+    pub fn config_axis_set_drive_slave_position(&mut self, position: u32, value: i32) -> Reply {
+        self.config_axis_set_integer(position, "drive_slave_position", value)
     }
 
     /// config.group.set (drive_channel)
-    pub fn config_axis_set_drive_channel(&mut self, position: u32, value: i32) {
-        self.config_axis_set_integer(position, "drive_channel", value);
+    pub fn config_axis_set_drive_channel(&mut self, position: u32, value: i32) -> Reply {
+        self.config_axis_set_integer(position, "drive_channel", value)
     }
 
     /// config.group.set (ext_encoder_alias)
-    pub fn config_axis_set_ext_encoder_alias(&mut self, position: u32, value: i32) {
-        self.config_axis_set_integer(position, "ext_encoder_alias", value);
+    pub fn config_axis_set_ext_encoder_alias(&mut self, position: u32, value: i32) -> Reply {
+        self.config_axis_set_integer(position, "ext_encoder_alias", value)
     }
 
     /// config.group.set (ext_encoder_slave_position)
-    pub fn config_axis_set_ext_encoder_slave_position(&mut self, position: u32, value: i32) {
-        self.config_axis_set_integer(position, "ext_encoder_slave_position", value);
+    pub fn config_axis_set_ext_encoder_slave_position(
+        &mut self,
+        position: u32,
+        value: i32,
+    ) -> Reply {
+        self.config_axis_set_integer(position, "ext_encoder_slave_position", value)
     }
 
     /// config.gr#[no_mangle]
-    pub fn config_axis_set_ext_encoder_channel(&mut self, position: u32, value: i32) {
-        self.config_axis_set_integer(position, "ext_encoder_channel", value);
+    pub fn config_axis_set_ext_encoder_channel(&mut self, position: u32, value: i32) -> Reply {
+        self.config_axis_set_integer(position, "ext_encoder_channel", value)
     }
 
     /// config.axis.get
-    pub fn config_axis_get(&mut self, position: u32) {
+    pub fn config_axis_get(&mut self, position: u32) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"config.axis.get","params":{"position":"#.to_owned()
             + position.to_string().as_str()
             + r#"}}"#;
-        self.send_message(&msg);
+        self.send_request(&msg)
     }
 
     /// save config
-    pub fn config_save(&mut self) {
+    pub fn config_save(&mut self) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"config.save"}"#;
-        self.send_message(msg);
+        self.send_request(msg)
     }
 
     /// System poweroff
-    pub fn poweroff(&mut self) {
+    pub fn poweroff(&mut self) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"system.poweroff"}"#;
-        self.send_message(msg);
+        self.send_request(msg)
     }
 
     /// System reboot
-    pub fn reboot(&mut self) {
+    pub fn reboot(&mut self) -> Reply {
         let msg = r#"{"jsonrpc":"2.0","method":"system.reboot"}"#;
-        self.send_message(msg);
+        self.send_request(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn response_completes_matching_reply() {
+        let mut botnana = Botnana::new();
+        let (id, reply) = botnana.new_reply();
+        let (_, other) = botnana.new_reply();
+        let response = format!(r#"{{"jsonrpc":"2.0","result":"1.0","id":{}}}"#, id);
+        assert!(botnana.handle_response(&response));
+        assert_eq!(reply.wait(Duration::from_millis(0)), Ok(Value::from("1.0")));
+        assert!(other.try_get().is_none());
+    }
+
+    #[test]
+    fn response_error_fails_reply() {
+        let mut botnana = Botnana::new();
+        let (id, reply) = botnana.new_reply();
+        let response = format!(
+            r#"{{"jsonrpc":"2.0","error":{{"code":-32601,"message":"Method not found"}},"id":{}}}"#,
+            id
+        );
+        assert!(botnana.handle_response(&response));
+        match reply.try_get() {
+            Some(Err(Error::Rpc { .. })) => {}
+            _ => panic!("reply should fail with the JSON-RPC error"),
+        }
+    }
+
+    #[test]
+    fn unknown_or_late_id_is_ignored() {
+        let mut botnana = Botnana::new();
+        let (id, reply) = botnana.new_reply();
+        let late = format!(r#"{{"jsonrpc":"2.0","result":null,"id":{}}}"#, id + 100);
+        assert!(botnana.handle_response(&late));
+        assert!(reply.try_get().is_none());
+
+        // 已經完成的 request 再收到回應也不影響
+        let response = format!(r#"{{"jsonrpc":"2.0","result":1,"id":{}}}"#, id);
+        assert!(botnana.handle_response(&response));
+        assert!(botnana.handle_response(&response));
+        assert_eq!(reply.try_get(), Some(Ok(Value::from(1))));
+        assert!(!botnana.handle_response("version|1.0"));
+    }
+
+    #[test]
+    fn dropping_reply_removes_pending() {
+        let botnana = Botnana::new();
+        let (id, reply) = botnana.new_reply();
+        assert!(botnana.pending_requests.lock().unwrap().contains_key(&id));
+        drop(reply);
+        assert!(botnana.pending_requests.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn cancel_fails_pending_replies() {
+        let botnana = Botnana::new();
+        let (_, first) = botnana.new_reply();
+        let (_, second) = botnana.new_reply();
+        botnana.cancel_requests();
+        assert!(botnana.pending_requests.lock().unwrap().is_empty());
+        assert_eq!(first.wait(Duration::from_millis(0)), Err(Error::NotConnected));
+        assert_eq!(second.try_get(), Some(Err(Error::NotConnected)));
+    }
+
+    #[test]
+    fn abort_code_fails_reply() {
+        let botnana = Botnana::new();
+        let (id, reply) = botnana.new_reply();
+        let tag = format!("error.{}", id);
+        botnana.handle_evaluation_event(&Record::new("evaluating", &id.to_string()));
+        botnana.handle_evaluation_event(&Record::new(&tag, "-13 "));
+        match reply.try_get() {
+            Some(Err(Error::ForthAbort(code))) => assert_eq!(code, "-13"),
+            _ => panic!("reply should fail with the abort code"),
+        }
+        assert_eq!(*botnana.evaluating.lock().unwrap(), None);
+    }
+
+    #[test]
+    fn compile_error_fails_running_evaluation() {
+        let botnana = Botnana::new();
+        let (id, reply) = botnana.new_reply();
+        let (_, other) = botnana.new_reply();
+        botnana.handle_evaluation_event(&Record::new("evaluating", &id.to_string()));
+        botnana.handle_evaluation_event(&Record::new("error", "Undefined word"));
+        match reply.try_get() {
+            Some(Err(Error::ForthAbort(msg))) => assert_eq!(msg, "Undefined word"),
+            _ => panic!("reply should fail with the error message"),
+        }
+        assert!(other.try_get().is_none());
+
+        // 沒有正在執行的 request 時，沒有 id 的 error 與 Reply 無關
+        botnana.handle_evaluation_event(&Record::new("error", "Undefined word"));
+        assert!(other.try_get().is_none());
     }
 }
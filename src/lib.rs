@@ -1,28 +1,52 @@
 extern crate libc;
+#[cfg(feature = "esi")]
+extern crate roxmltree;
+#[cfg(feature = "serde")]
+extern crate serde;
 extern crate serde_json;
 extern crate url;
 extern crate ws;
 
-/// alias, EtherCAT Slave 的站號別名。
-/// position, EtherCAT Slave 的站號，最靠近主站的為 1, 依序遞增排列。
-/// 使用規則，當 alias > 0，就以 alias 選定從站，
-/// 當 alias = 0, 就以 position 選定從站。  
-macro_rules! slave_position {
-    ( $alias:expr, $position:expr) => {{
-        if $alias > 0 {
-            format!("{} ec-a>n", $alias)
-        } else {
-            format!("{}", $position)
-        }
-    }};
-}
-
+pub mod address;
+pub mod al_state;
+#[cfg(feature = "async")]
+pub mod async_api;
 pub mod botnana;
+pub mod callback_api;
+pub mod cia402;
 pub mod data_pool;
 pub mod drive_api;
+pub mod error;
+#[cfg(feature = "esi")]
+pub mod esi;
 pub mod ethercat_api;
+pub mod event;
+pub mod history;
+pub mod homing;
 pub mod json_api;
+pub mod motion;
 pub mod program;
+pub mod protocol;
+pub mod reconnect;
+pub mod sdo;
+pub mod topology;
 
+pub use address::{DriveAddr, DriveTarget, SlaveAddr};
+pub use al_state::AlState;
+#[cfg(feature = "async")]
+pub use async_api::AsyncBotnana;
 pub use botnana::Botnana;
+pub use cia402::{Cia402State, OperationMode};
+pub use data_pool::BusStatus;
+pub use error::{Error, Result};
+#[cfg(feature = "esi")]
+pub use esi::{EsiDevice, EsiLibrary};
+pub use event::{DriveChange, SubscriptionId, TagEvent};
+pub use history::{History, Sample, Signal};
+pub use homing::HomingJob;
+pub use json_api::Reply;
+pub use motion::MotionHandle;
 pub use program::Program;
+pub use reconnect::ReconnectPolicy;
+pub use sdo::{DataType, SdoValue};
+pub use topology::{SlaveInfo, Topology, TopologyDiff};
@@ -0,0 +1,243 @@
+use address::DriveTarget;
+use botnana::Botnana;
+use cia402::{Cia402State, OperationMode, STATUS_OP_MODE_BIT_12};
+use data_pool::Drive;
+use drive_api::target_position_script;
+use error::{Error, Result};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// 檢查運動狀態的間隔
+const MOTION_POLL_INTERVAL_MS: u64 = 10;
+
+/// Motion Handle
+/// 送出運動命令後取得的 handle，運動完成 (target reached) 時收到 `Ok(())`，
+/// 驅動器離開 Operation Enabled、發生 following error 或逾時則收到對應的錯誤
+pub struct MotionHandle {
+    receiver: mpsc::Receiver<Result<()>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl MotionHandle {
+    /// 等待運動結束
+    /// 不可在 callback 內呼叫，data pool 由處理 callback 的 thread 更新
+    pub fn wait(self) -> Result<()> {
+        self.receiver.recv().unwrap_or(Err(Error::NotConnected))
+    }
+
+    /// 查詢運動是否已結束，不會等待
+    pub fn try_get(&self) -> Option<Result<()>> {
+        match self.receiver.try_recv() {
+            Ok(x) => Some(x),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(Error::NotConnected)),
+        }
+    }
+
+    /// 已經有結果的 handle
+    fn resolved(result: Result<()>) -> MotionHandle {
+        let (sender, receiver) = mpsc::channel();
+        let _ = sender.send(result);
+        MotionHandle {
+            receiver,
+            cancelled: Arc::new(AtomicBool::new(true)),
+        }
+    }
+}
+
+impl Drop for MotionHandle {
+    fn drop(&mut self) {
+        // 不再等待結果，停止監看運動狀態
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+}
+
+/// 判斷 PP 運動是否完成
+/// 命令送出前 target reached 可能還是上一次運動的結果，所以要等驅動器接受新的 set-point
+/// (set-point acknowledge bit 12 改變，或送出前已停止的驅動器清除 target reached)，
+/// 之後再看到 target reached 才算完成
+struct MotionMonitor {
+    /// 送出命令前的 set-point acknowledge
+    start_ack: u16,
+    /// 送出命令前是否已到達目標 (上一次運動已結束)
+    start_reached: bool,
+    /// 驅動器是否已接受新的 set-point
+    acknowledged: bool,
+}
+
+impl MotionMonitor {
+    /// 以送出命令前的驅動器狀態建立，驅動器不在 PP 模式時回傳 `Error::OperationMode`
+    fn new(drive: &Drive) -> Result<MotionMonitor> {
+        match drive.operation_mode() {
+            Some(OperationMode::ProfilePosition) => Ok(MotionMonitor {
+                start_ack: drive.status_word & STATUS_OP_MODE_BIT_12,
+                start_reached: drive.is_target_reached(),
+                acknowledged: false,
+            }),
+            mode => Err(Error::OperationMode(mode)),
+        }
+    }
+
+    /// 依目前的驅動器狀態判斷，運動還在進行時為 None
+    fn step(&mut self, drive: &Drive) -> Option<Result<()>> {
+        let state = drive.cia402_state();
+        if state != Cia402State::OperationEnabled {
+            return Some(Err(Error::DriveState(state)));
+        }
+        if drive.operation_mode() != Some(OperationMode::ProfilePosition) {
+            return Some(Err(Error::OperationMode(drive.operation_mode())));
+        }
+        if drive.has_following_error() {
+            return Some(Err(Error::FollowingError));
+        }
+        let reached = drive.is_target_reached();
+        self.acknowledged = self.acknowledged
+            || drive.status_word & STATUS_OP_MODE_BIT_12 != self.start_ack
+            || (self.start_reached && !reached);
+        if self.acknowledged && reached {
+            Some(Ok(()))
+        } else {
+            None
+        }
+    }
+}
+
+impl Botnana {
+    /// PP 模式下進行運動，並回傳監看運動狀態的 MotionHandle (需要先啟動自動取得資料的功能)
+    /// 驅動器不在 PP 模式時不送出命令，handle 收到 `Error::OperationMode`
+    /// @drive    : 驅動器位址或 axis 名稱
+    /// @relative : 相對位置或是絕對位置，有些驅動器不支援相對位置
+    /// @target   : 目標位置 [pulse]
+    /// @timeout  : 等待運動完成的時間
+    pub fn drive_move<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        relative: bool,
+        target: i32,
+        timeout: Duration,
+    ) -> MotionHandle {
+        let addr = match self.resolve_drive(drive) {
+            Ok(x) => x,
+            Err(e) => return MotionHandle::resolved(Err(e)),
+        };
+        let mut monitor = match self.snapshot().drive(&addr).map(MotionMonitor::new) {
+            Some(Ok(x)) => x,
+            Some(Err(e)) => return MotionHandle::resolved(Err(e)),
+            None => return MotionHandle::resolved(Err(Error::DriveNotFound)),
+        };
+        let script = target_position_script(&addr, relative, i64::from(target));
+        if let Err(e) = self.try_send_script_to_buffer(&script) {
+            return MotionHandle::resolved(Err(e));
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let handle = MotionHandle {
+            receiver,
+            cancelled: cancelled.clone(),
+        };
+        let botnana = self.clone();
+        let deadline = Instant::now() + timeout;
+        let spawned = thread::Builder::new()
+            .name("MOTION".to_string())
+            .spawn(move || {
+                while !cancelled.load(Ordering::SeqCst) {
+                    let result = match botnana.snapshot().drive(&addr) {
+                        Some(drive) => monitor.step(drive),
+                        None => Some(Err(Error::DriveNotFound)),
+                    };
+                    if let Some(result) = result {
+                        let _ = sender.send(result);
+                        return;
+                    }
+                    if Instant::now() >= deadline {
+                        let _ = sender.send(Err(Error::Timeout));
+                        return;
+                    }
+                    thread::sleep(Duration::from_millis(MOTION_POLL_INTERVAL_MS));
+                }
+            });
+        match spawned {
+            Ok(_) => handle,
+            Err(e) => MotionHandle::resolved(Err(Error::Io(e.to_string()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cia402::{STATUS_OP_MODE_BIT_13, STATUS_TARGET_REACHED};
+
+    /// Operation Enabled 的 status word
+    const ENABLED: u16 = 0x0027;
+
+    fn drive(status_word: u16) -> Drive {
+        let mut drive = Drive::new();
+        drive.status_word = status_word;
+        drive.op_mode = i8::from(OperationMode::ProfilePosition);
+        drive
+    }
+
+    #[test]
+    fn requires_pp_mode() {
+        let mut other = drive(ENABLED);
+        other.op_mode = i8::from(OperationMode::ProfileVelocity);
+        assert_eq!(
+            MotionMonitor::new(&other).err(),
+            Some(Error::OperationMode(Some(OperationMode::ProfileVelocity)))
+        );
+        let mut monitor = MotionMonitor::new(&drive(ENABLED)).unwrap();
+        assert_eq!(
+            monitor.step(&other),
+            Some(Err(Error::OperationMode(Some(OperationMode::ProfileVelocity))))
+        );
+    }
+
+    #[test]
+    fn completes_after_acknowledge() {
+        let idle = drive(ENABLED | STATUS_TARGET_REACHED);
+        let mut monitor = MotionMonitor::new(&idle).unwrap();
+        // 驅動器還沒處理新的 set-point
+        assert_eq!(monitor.step(&idle), None);
+        // 接受 set-point 後開始運動
+        assert_eq!(monitor.step(&drive(ENABLED | STATUS_OP_MODE_BIT_12)), None);
+        assert_eq!(monitor.step(&drive(ENABLED)), None);
+        assert_eq!(monitor.step(&idle), Some(Ok(())));
+    }
+
+    #[test]
+    fn previous_move_still_running() {
+        // 送出前上一次運動還沒結束，target reached 為 0 不代表接受了新的 set-point
+        let moving = drive(ENABLED);
+        let mut monitor = MotionMonitor::new(&moving).unwrap();
+        assert_eq!(monitor.step(&moving), None);
+        // 上一次運動到達，但還沒接受新的 set-point
+        assert_eq!(monitor.step(&drive(ENABLED | STATUS_TARGET_REACHED)), None);
+        assert_eq!(monitor.step(&drive(ENABLED | STATUS_OP_MODE_BIT_12)), None);
+        assert_eq!(
+            monitor.step(&drive(ENABLED | STATUS_TARGET_REACHED)),
+            Some(Ok(()))
+        );
+    }
+
+    #[test]
+    fn stops_on_errors() {
+        let mut monitor = MotionMonitor::new(&drive(ENABLED)).unwrap();
+        assert_eq!(
+            monitor.step(&drive(ENABLED | STATUS_OP_MODE_BIT_13)),
+            Some(Err(Error::FollowingError))
+        );
+        assert_eq!(
+            monitor.step(&drive(0x0008)),
+            Some(Err(Error::DriveState(Cia402State::Fault)))
+        );
+    }
+}
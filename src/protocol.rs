@@ -0,0 +1,216 @@
+use std::str::Split;
+
+/// 一筆 `tag|value` 資料，內容都是原始訊息的切片
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Record<'a> {
+    /// 完整的 tag (例如 `real_position.1.2`)
+    pub tag: &'a str,
+    /// tag 名稱 (例如 `real_position`)
+    pub name: &'a str,
+    /// tag 的值
+    pub value: &'a str,
+}
+
+impl<'a> Record<'a> {
+    /// New
+    pub fn new(tag: &'a str, value: &'a str) -> Record<'a> {
+        Record {
+            tag,
+            name: tag.split('.').next().unwrap_or(""),
+            value,
+        }
+    }
+
+    /// tag 名稱之後以 `.` 分隔的索引
+    pub fn indices(&self) -> Indices<'a> {
+        let mut fields = self.tag.split('.');
+        fields.next();
+        Indices { fields }
+    }
+
+    /// Slave position (最後一個索引)，沒有或無法解析時為 0
+    pub fn position(&self) -> usize {
+        self.slave_index()[0]
+    }
+
+    /// Channel (`name.channel.position` 中的 channel)，沒有或無法解析時為 0
+    pub fn channel(&self) -> usize {
+        self.slave_index()[1]
+    }
+
+    /// 只使用前兩個索引，`name.p` 為 position，`name.c.p` 為 channel 與 position
+    fn slave_index(&self) -> [usize; 2] {
+        let mut indices = self.indices();
+        match (indices.next(), indices.next()) {
+            (Some(position), None) => [position.unwrap_or(0), 0],
+            (Some(channel), Some(position)) => [position.unwrap_or(0), channel.unwrap_or(0)],
+            _ => [0, 0],
+        }
+    }
+}
+
+/// tag 索引的 iterator，無法解析成數字的索引為 None
+pub struct Indices<'a> {
+    fields: Split<'a, char>,
+}
+
+impl<'a> Iterator for Indices<'a> {
+    type Item = Option<usize>;
+
+    fn next(&mut self) -> Option<Option<usize>> {
+        self.fields.next().map(|x| x.parse::<usize>().ok())
+    }
+}
+
+/// `tag|value` 資料的 iterator
+pub struct Records<'a> {
+    lines: Split<'a, char>,
+    fields: Option<Split<'a, char>>,
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Record<'a>;
+
+    fn next(&mut self) -> Option<Record<'a>> {
+        loop {
+            if let Some(ref mut fields) = self.fields {
+                if let Some(tag) = fields.next() {
+                    // 最後只有 tag 沒有值的欄位直接忽略
+                    if let Some(value) = fields.next() {
+                        let tag = tag.trim_start();
+                        if !tag.is_empty() {
+                            return Some(Record::new(tag, value));
+                        }
+                    }
+                    continue;
+                }
+            }
+            let line = self.lines.next()?;
+            // 每一行開頭可能有空白與 `|`
+            let line = line.trim_end_matches('\r').trim_start().trim_start_matches('|');
+            self.fields = Some(line.split('|'));
+        }
+    }
+}
+
+/// 解析訊息，依序取出每一筆 `tag|value`
+/// Server 送來的訊息以換行分隔，每一行是 `tag|value|tag|value...`，
+/// tag 的格式為 `name.channel.position` (索引可省略)
+pub fn parse(message: &str) -> Records<'_> {
+    Records {
+        lines: message.split('\n'),
+        fields: None,
+    }
+}
+
+/// 判斷 tag 是否符合 glob pattern
+/// pattern 與 tag 都以 `.` 分段，段數必須相同，每一段中 `*` 代表任意字元，`?` 代表一個字元
+/// 例如 `real_position.*.3` 符合 slave 3 所有 channel 的 real_position，`*.2.1` 符合 slave 1 channel 2 的所有 tag
+pub fn tag_matches(pattern: &str, tag: &str) -> bool {
+    let mut patterns = pattern.split('.');
+    let mut fields = tag.split('.');
+    loop {
+        match (patterns.next(), fields.next()) {
+            (Some(p), Some(f)) => {
+                if !glob_matches(p.as_bytes(), f.as_bytes()) {
+                    return false;
+                }
+            }
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// 單一段落的 glob 比對
+fn glob_matches(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // 最近一次 `*` 的位置，比對失敗時從這裡重新開始
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == b'?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == b'*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((sp, st)) = star {
+            p = sp + 1;
+            t = st + 1;
+            star = Some((sp, st + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|x| *x == b'*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_pairs() {
+        let records: Vec<Record> = parse("|version|1.0|slaves_responding|2").collect();
+        assert_eq!(
+            records,
+            vec![
+                Record::new("version", "1.0"),
+                Record::new("slaves_responding", "2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_lines() {
+        let records: Vec<Record> = parse("a|1\r\n |b|2\n\nc|3").collect();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1].tag, "b");
+        assert_eq!(records[2].value, "3");
+    }
+
+    #[test]
+    fn ignore_odd_trailing_field() {
+        let records: Vec<Record> = parse("a|1|b|2|dangling\n||c|3").collect();
+        assert_eq!(
+            records,
+            vec![
+                Record::new("a", "1"),
+                Record::new("b", "2"),
+                Record::new("c", "3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn tag_indices() {
+        let record = Record::new("real_position.1.2", "100");
+        assert_eq!(record.name, "real_position");
+        assert_eq!(record.channel(), 1);
+        assert_eq!(record.position(), 2);
+
+        let record = Record::new("vendor.3", "0x1");
+        assert_eq!(record.position(), 3);
+        assert_eq!(record.channel(), 0);
+
+        let record = Record::new("version", "1.0");
+        assert_eq!(record.indices().count(), 0);
+        assert_eq!(record.position(), 0);
+
+        let record = Record::new("x.a.4", "");
+        assert_eq!(record.indices().collect::<Vec<_>>(), vec![None, Some(4)]);
+        assert_eq!(record.position(), 4);
+    }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(tag_matches("real_position.1.3", "real_position.1.3"));
+        assert!(tag_matches("real_position.*.3", "real_position.2.3"));
+        assert!(tag_matches("*.2.1", "status_word.2.1"));
+        assert!(tag_matches("*_position.*.*", "target_position.1.10"));
+        assert!(tag_matches("slave_state.?", "slave_state.4"));
+        assert!(!tag_matches("real_position.*.3", "real_position.2.13"));
+        assert!(!tag_matches("real_position.*", "real_position.2.3"));
+        assert!(!tag_matches("*.2.1", "version"));
+    }
+}
@@ -0,0 +1,292 @@
+use botnana::Botnana;
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// 檢查重新連線結果的間隔
+const RECONNECT_CHECK_INTERVAL_MS: u64 = 10;
+
+/// 重新連線等待時間的亂數來源 (xorshift64*)
+/// 每個重新連線的 thread 只 seed 一次，測試時可以用固定的 seed
+pub(crate) struct JitterSource {
+    state: u64,
+}
+
+impl JitterSource {
+    /// 以固定的 seed 建立
+    pub(crate) fn new(seed: u64) -> JitterSource {
+        // xorshift 的 state 不可為 0
+        JitterSource {
+            state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed },
+        }
+    }
+
+    /// 以每個 process 不同的 RandomState 與目前時間作為 seed
+    pub(crate) fn from_entropy() -> JitterSource {
+        let mut hasher = RandomState::new().build_hasher();
+        if let Ok(x) = SystemTime::now().duration_since(UNIX_EPOCH) {
+            hasher.write_u128(x.as_nanos());
+        }
+        JitterSource::new(hasher.finish())
+    }
+
+    /// 0.0 ~ 1.0 的亂數
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        let x = self.state.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Reconnect Policy
+/// 連線中斷後自動重新連線的設定，每次失敗後等待時間乘上 `multiplier`，最多等待 `max_delay`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReconnectPolicy {
+    /// 第一次重新連線前等待的時間
+    pub initial_delay: Duration,
+    /// 等待時間的上限
+    pub max_delay: Duration,
+    /// 每次失敗後等待時間的倍數
+    pub multiplier: u32,
+    /// 最多嘗試次數，0 表示不限制
+    pub max_attempts: u32,
+    /// 等待時間隨機變動的比例 (0.0 ~ 1.0)，例如 0.2 表示在 ±20% 內變動，避免多台設備同時重新連線
+    pub jitter: f64,
+}
+
+impl ReconnectPolicy {
+    /// New (500 ms 開始，每次加倍，最多等待 30 s，不限制次數，沒有隨機變動)
+    pub fn new() -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            multiplier: 2,
+            max_attempts: 0,
+            jitter: 0.0,
+        }
+    }
+
+    /// 第 attempt 次 (從 0 開始) 重新連線前要等待的時間
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let mut delay = self.initial_delay;
+        for _ in 0..attempt {
+            delay = match delay.checked_mul(self.multiplier) {
+                Some(x) if x < self.max_delay => x,
+                _ => return self.max_delay,
+            };
+        }
+        delay.min(self.max_delay)
+    }
+
+    /// 第 attempt 次重新連線前實際等待的時間，依 `jitter` 加上 `source` 產生的隨機變動
+    pub(crate) fn jittered_delay(&self, attempt: u32, source: &mut JitterSource) -> Duration {
+        self.apply_jitter(self.delay(attempt), source.next_f64())
+    }
+
+    /// `random` 為 0.0 ~ 1.0 的亂數，0.5 時不變動
+    fn apply_jitter(&self, delay: Duration, random: f64) -> Duration {
+        // jitter 為 0 或 NaN 時不變動
+        if self.jitter.is_nan() || self.jitter <= 0.0 {
+            return delay;
+        }
+        let jitter = self.jitter.min(1.0);
+        delay.mul_f64(1.0 + jitter * (2.0 * random.clamp(0.0, 1.0) - 1.0))
+    }
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> ReconnectPolicy {
+        ReconnectPolicy::new()
+    }
+}
+
+impl Botnana {
+    /// Set reconnect policy
+    /// 設為 None 時關閉自動重新連線 (預設)
+    /// 重新連線後會重新執行 data pool 的初始化查詢，已註冊的 tag callbacks 保留在本地端，不需要重新註冊
+    pub fn set_reconnect_policy(&mut self, policy: Option<ReconnectPolicy>) {
+        *self.reconnect_policy.lock().expect("set_reconnect_policy") = policy;
+    }
+
+    /// Reconnect policy
+    pub fn reconnect_policy(&self) -> Option<ReconnectPolicy> {
+        *self.reconnect_policy.lock().expect("reconnect_policy")
+    }
+
+    /// Is reconnecting ?
+    pub fn is_reconnecting(&self) -> bool {
+        *self.is_reconnecting.lock().expect("is_reconnecting")
+    }
+
+    /// WS 連線結束後，依 reconnect policy 啟動重新連線的 thread
+    pub(crate) fn schedule_reconnect(&self) {
+        if *self.manual_disconnect.lock().expect("schedule_reconnect") {
+            return;
+        }
+        let policy = match self.reconnect_policy() {
+            Some(x) => x,
+            None => return,
+        };
+        {
+            let mut is_reconnecting = self.is_reconnecting.lock().expect("schedule_reconnect");
+            // 重新連線的 thread 會自己處理後續的失敗
+            if *is_reconnecting {
+                return;
+            }
+            *is_reconnecting = true;
+        }
+
+        let mut bna = self.clone();
+        let result = thread::Builder::new()
+            .name("RECONNECT".to_string())
+            .spawn(move || {
+                let mut jitter = JitterSource::from_entropy();
+                let mut attempt = 0;
+                loop {
+                    if policy.max_attempts > 0 && attempt >= policy.max_attempts {
+                        bna.execute_on_reconnect_cb(&format!(
+                            "Give up reconnecting to {} after {} attempts",
+                            bna.url(),
+                            attempt
+                        ));
+                        break;
+                    }
+                    thread::sleep(policy.jittered_delay(attempt, &mut jitter));
+                    // 使用者已中斷連線或關閉重新連線
+                    if *bna.manual_disconnect.lock().expect("RECONNECT")
+                        || bna.reconnect_policy().is_none()
+                    {
+                        break;
+                    }
+                    attempt += 1;
+                    bna.execute_on_reconnect_cb(&format!(
+                        "Reconnecting to {} (attempt {})",
+                        bna.url(),
+                        attempt
+                    ));
+                    if bna.try_connect().is_err() {
+                        continue;
+                    }
+                    // 等待連線成功或失敗
+                    while !bna.is_connected() && *bna.is_connecting.lock().expect("RECONNECT") {
+                        thread::sleep(Duration::from_millis(RECONNECT_CHECK_INTERVAL_MS));
+                    }
+                    if bna.is_connected() {
+                        bna.restart_auto_query();
+                        bna.execute_on_reconnect_cb(&format!(
+                            "Reconnected to {} (attempt {})",
+                            bna.url(),
+                            attempt
+                        ));
+                        // 確認仍在連線中才清除旗標，之後連線中斷時 schedule_reconnect 會再啟動重新連線
+                        let mut is_reconnecting = bna.is_reconnecting.lock().expect("RECONNECT");
+                        if bna.is_connected() {
+                            *is_reconnecting = false;
+                            return;
+                        }
+                        // 清除旗標前連線又中斷了，繼續重新連線
+                    }
+                }
+                *bna.is_reconnecting.lock().expect("RECONNECT") = false;
+            });
+        if let Err(e) = result {
+            *self.is_reconnecting.lock().expect("schedule_reconnect") = false;
+            self.execute_on_reconnect_cb(&format!("Can't create RECONNECT thread ({})", e));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+            multiplier: 2,
+            max_attempts: 0,
+            jitter: 0.0,
+        }
+    }
+
+    #[test]
+    fn delay_grows() {
+        let policy = policy();
+        assert_eq!(policy.delay(0), Duration::from_millis(100));
+        assert_eq!(policy.delay(1), Duration::from_millis(200));
+        assert_eq!(policy.delay(2), Duration::from_millis(400));
+        assert_eq!(policy.delay(3), Duration::from_millis(800));
+    }
+
+    #[test]
+    fn delay_capped() {
+        let policy = policy();
+        assert_eq!(policy.delay(4), Duration::from_millis(1000));
+        assert_eq!(policy.delay(1000), Duration::from_millis(1000));
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::from_secs(5),
+            ..policy
+        };
+        assert_eq!(policy.delay(0), Duration::from_millis(1000));
+    }
+
+    #[test]
+    fn delay_constant_multiplier() {
+        let policy = ReconnectPolicy {
+            multiplier: 1,
+            ..policy()
+        };
+        assert_eq!(policy.delay(10), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn jitter_bounds() {
+        let policy = ReconnectPolicy {
+            jitter: 0.2,
+            ..policy()
+        };
+        let delay = Duration::from_millis(1000);
+        assert_eq!(policy.apply_jitter(delay, 0.0), Duration::from_millis(800));
+        assert_eq!(policy.apply_jitter(delay, 0.5), Duration::from_millis(1000));
+        assert_eq!(policy.apply_jitter(delay, 1.0), Duration::from_millis(1200));
+        let mut source = JitterSource::new(7);
+        for attempt in 0..10 {
+            let delay = policy.jittered_delay(attempt, &mut source);
+            assert!(delay >= policy.delay(attempt).mul_f64(0.8));
+            assert!(delay <= policy.delay(attempt).mul_f64(1.2));
+        }
+    }
+
+    #[test]
+    fn no_jitter() {
+        let policy = policy();
+        let mut source = JitterSource::new(7);
+        assert_eq!(policy.jittered_delay(2, &mut source), policy.delay(2));
+    }
+
+    #[test]
+    fn jitter_source() {
+        // 相同的 seed 產生相同的序列
+        let first: Vec<f64> = {
+            let mut source = JitterSource::new(42);
+            (0..100).map(|_| source.next_f64()).collect()
+        };
+        let mut source = JitterSource::new(42);
+        for x in first.iter() {
+            assert_eq!(source.next_f64(), *x);
+        }
+        assert!(first.iter().all(|x| *x >= 0.0 && *x < 1.0));
+        // 數值有分散開來
+        assert!(first.iter().any(|x| *x < 0.25));
+        assert!(first.iter().any(|x| *x > 0.75));
+        // seed 為 0 時仍可使用
+        let mut source = JitterSource::new(0);
+        assert_ne!(source.next_f64(), source.next_f64());
+    }
+}
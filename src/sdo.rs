@@ -0,0 +1,285 @@
+use address::DriveTarget;
+use botnana::Botnana;
+use error::{Error, Result};
+use std::time::Duration;
+
+/// SDO 資料型態
+/// 只支援 32 bit 以內的固定長度型態
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DataType {
+    I8,
+    I16,
+    I32,
+    U8,
+    U16,
+    U32,
+    F32,
+}
+
+impl DataType {
+    /// 資料的 byte 數
+    pub fn size(&self) -> usize {
+        match *self {
+            DataType::I8 | DataType::U8 => 1,
+            DataType::I16 | DataType::U16 => 2,
+            DataType::I32 | DataType::U32 | DataType::F32 => 4,
+        }
+    }
+}
+
+/// SDO 資料
+#[derive(Debug, Clone, PartialEq)]
+pub enum SdoValue {
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    F32(f32),
+}
+
+impl SdoValue {
+    /// 資料型態
+    pub fn data_type(&self) -> DataType {
+        match *self {
+            SdoValue::I8(_) => DataType::I8,
+            SdoValue::I16(_) => DataType::I16,
+            SdoValue::I32(_) => DataType::I32,
+            SdoValue::U8(_) => DataType::U8,
+            SdoValue::U16(_) => DataType::U16,
+            SdoValue::U32(_) => DataType::U32,
+            SdoValue::F32(_) => DataType::F32,
+        }
+    }
+
+    /// 轉成 CoE 的資料格式 (little endian)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match *self {
+            SdoValue::I8(x) => x.to_le_bytes().to_vec(),
+            SdoValue::I16(x) => x.to_le_bytes().to_vec(),
+            SdoValue::I32(x) => x.to_le_bytes().to_vec(),
+            SdoValue::U8(x) => vec![x],
+            SdoValue::U16(x) => x.to_le_bytes().to_vec(),
+            SdoValue::U32(x) => x.to_le_bytes().to_vec(),
+            SdoValue::F32(x) => x.to_bits().to_le_bytes().to_vec(),
+        }
+    }
+
+    /// 由 CoE 的資料格式 (little endian) 解碼
+    pub fn from_bytes(data_type: DataType, bytes: &[u8]) -> Result<SdoValue> {
+        let size = data_type.size();
+        if bytes.len() != size {
+            return Err(Error::Parse(format!(
+                "Invalid SDO data length for {:?}: {} bytes",
+                data_type,
+                bytes.len()
+            )));
+        }
+        let mut buf = [0u8; 4];
+        buf[..size].copy_from_slice(bytes);
+        let x = u32::from_le_bytes(buf);
+        Ok(match data_type {
+            DataType::I8 => SdoValue::I8(x as i8),
+            DataType::I16 => SdoValue::I16(x as i16),
+            DataType::I32 => SdoValue::I32(x as i32),
+            DataType::U8 => SdoValue::U8(x as u8),
+            DataType::U16 => SdoValue::U16(x as u16),
+            DataType::U32 => SdoValue::U32(x),
+            DataType::F32 => SdoValue::F32(f32::from_bits(x)),
+        })
+    }
+}
+
+/// Typed SDO word 的型態名稱，F32 以 u32 的 bit pattern 傳輸
+/// 韌體的 typed SDO words (參考 Botnana Control 的 EtherCAT SDO 指令):
+/// `sdo-upload-{type} ( subindex index ch slave -- )`
+/// `sdo-download-{type} ( n subindex index ch slave -- )`
+/// `until-no-requests ( -- )` 等待所有 SDO request 完成
+/// `sdo-error? ( ch slave -- flag )` 最近一次 SDO 傳輸是否中止
+/// `sdo-data@ ( ch slave -- n )` 最近一次 SDO upload 的資料
+fn typed_word(data_type: DataType) -> &'static str {
+    match data_type {
+        DataType::I8 => "i8",
+        DataType::I16 => "i16",
+        DataType::I32 => "i32",
+        DataType::U8 => "u8",
+        DataType::U16 => "u16",
+        DataType::U32 | DataType::F32 => "u32",
+    }
+}
+
+/// 轉成 Forth 的 cell
+fn to_cell(value: &SdoValue) -> i64 {
+    match *value {
+        SdoValue::I8(x) => i64::from(x),
+        SdoValue::I16(x) => i64::from(x),
+        SdoValue::I32(x) => i64::from(x),
+        SdoValue::U8(x) => i64::from(x),
+        SdoValue::U16(x) => i64::from(x),
+        SdoValue::U32(x) => i64::from(x),
+        SdoValue::F32(x) => i64::from(x.to_bits()),
+    }
+}
+
+/// 解析 script 回傳的 `error data`，error 不為 0 時回傳 `Error::SdoAbort`
+fn parse_sdo_result(result: &str) -> Result<i64> {
+    let invalid = || Error::Parse(format!("Invalid SDO result: {}", result));
+    let mut fields = result.split_whitespace();
+    let code = fields
+        .next()
+        .and_then(|x| x.parse::<i64>().ok())
+        .ok_or_else(invalid)?;
+    if code != 0 {
+        return Err(Error::SdoAbort(code as u32));
+    }
+    fields
+        .next()
+        .map_or(Ok(0), |x| x.parse::<i64>().map_err(|_| invalid()))
+}
+
+impl Botnana {
+    /// SDO upload (讀取驅動器的 object)
+    /// 送出 `sdo-upload-{type}` 並等待完成，再由 `sdo-error?` 與 `sdo-data@` 取回結果
+    /// 有設定 ESI library 時 (feature `esi`)，先依 object dictionary 檢查 index 與資料型態
+    /// 不可在 callback 內呼叫，callback 與回應處理在同一個 thread
+    /// @drive     : 驅動器位址或 axis 名稱
+    /// @index     : object index
+    /// @subindex  : object subindex
+    /// @data_type : 資料型態
+    /// @timeout   : 等待的時間
+    pub fn sdo_read<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        index: u16,
+        subindex: u8,
+        data_type: DataType,
+        timeout: Duration,
+    ) -> Result<SdoValue> {
+        let addr = self.resolve_drive(drive)?;
+        let word = typed_word(data_type);
+        #[cfg(feature = "esi")]
+        self.validate_sdo(&addr, index, subindex, data_type, false)?;
+        let reply = self.try_evaluate_with(|id| {
+            format!(
+                "{subindex} ${index:04X} {channel} {slave} sdo-upload-{word} until-no-requests \
+                 10 emit .\" script_result.{id}|\" {channel} {slave} sdo-error? . \
+                 {channel} {slave} sdo-data@ . 10 emit",
+                subindex = subindex,
+                index = index,
+                channel = addr.channel,
+                slave = addr.slave,
+                word = word,
+                id = id,
+            )
+        })?;
+        let result = reply.wait(timeout)?;
+        let data = parse_sdo_result(result.as_str().unwrap_or(""))?;
+        SdoValue::from_bytes(data_type, &data.to_le_bytes()[..data_type.size()])
+    }
+
+    /// SDO download (寫入驅動器的 object)
+    /// 送出 `sdo-download-{type}` 並等待完成，再由 `sdo-error?` 確認沒有中止
+    /// 有設定 ESI library 時 (feature `esi`)，先依 object dictionary 檢查 index、資料型態與存取權限
+    /// 不可在 callback 內呼叫，callback 與回應處理在同一個 thread
+    /// @drive     : 驅動器位址或 axis 名稱
+    /// @index     : object index
+    /// @subindex  : object subindex
+    /// @value     : 寫入的資料，型態需與 object 相同
+    /// @timeout   : 等待的時間
+    pub fn sdo_write<A: Into<DriveTarget>>(
+        &mut self,
+        drive: A,
+        index: u16,
+        subindex: u8,
+        value: &SdoValue,
+        timeout: Duration,
+    ) -> Result<()> {
+        let addr = self.resolve_drive(drive)?;
+        let word = typed_word(value.data_type());
+        let data = to_cell(value);
+        #[cfg(feature = "esi")]
+        self.validate_sdo(&addr, index, subindex, value.data_type(), true)?;
+        let reply = self.try_evaluate_with(|id| {
+            format!(
+                "{data} {subindex} ${index:04X} {channel} {slave} sdo-download-{word} \
+                 until-no-requests 10 emit .\" script_result.{id}|\" \
+                 {channel} {slave} sdo-error? . 10 emit",
+                data = data,
+                subindex = subindex,
+                index = index,
+                channel = addr.channel,
+                slave = addr.slave,
+                word = word,
+                id = id,
+            )
+        })?;
+        let result = reply.wait(timeout)?;
+        parse_sdo_result(result.as_str().unwrap_or("")).map(|_| ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_round_trip() {
+        let values = [
+            SdoValue::I8(-2),
+            SdoValue::I16(-300),
+            SdoValue::I32(-70000),
+            SdoValue::U8(0xAB),
+            SdoValue::U16(0xABCD),
+            SdoValue::U32(0xDEAD_BEEF),
+            SdoValue::F32(1.5),
+        ];
+        for value in values.iter() {
+            let bytes = value.to_bytes();
+            assert_eq!(bytes.len(), value.data_type().size());
+            assert_eq!(SdoValue::from_bytes(value.data_type(), &bytes).as_ref(), Ok(value));
+        }
+    }
+
+    #[test]
+    fn bytes_little_endian() {
+        assert_eq!(SdoValue::U16(0x1234).to_bytes(), vec![0x34, 0x12]);
+        assert_eq!(SdoValue::I32(-1).to_bytes(), vec![0xFF; 4]);
+        assert_eq!(
+            SdoValue::from_bytes(DataType::I16, &[0xFE, 0xFF]),
+            Ok(SdoValue::I16(-2))
+        );
+        assert_eq!(
+            SdoValue::from_bytes(DataType::F32, &1.5f32.to_bits().to_le_bytes()),
+            Ok(SdoValue::F32(1.5))
+        );
+    }
+
+    #[test]
+    fn bytes_wrong_length() {
+        assert!(SdoValue::from_bytes(DataType::U16, &[0x01]).is_err());
+        assert!(SdoValue::from_bytes(DataType::U32, &[0x01, 0x02, 0x03]).is_err());
+        assert!(SdoValue::from_bytes(DataType::I32, &[0; 8]).is_err());
+        assert!(SdoValue::from_bytes(DataType::U8, &[]).is_err());
+    }
+
+    #[test]
+    fn typed_words() {
+        assert_eq!(typed_word(DataType::I8), "i8");
+        assert_eq!(typed_word(DataType::F32), "u32");
+        assert_eq!(to_cell(&SdoValue::I16(-2)), -2);
+        assert_eq!(to_cell(&SdoValue::U32(0xFFFF_FFFF)), 0xFFFF_FFFF);
+        assert_eq!(to_cell(&SdoValue::F32(1.0)), 0x3F80_0000);
+    }
+
+    #[test]
+    fn sdo_result() {
+        assert_eq!(parse_sdo_result("0 1234"), Ok(1234));
+        assert_eq!(parse_sdo_result(" 0 -2 "), Ok(-2));
+        assert_eq!(parse_sdo_result("0"), Ok(0));
+        assert_eq!(parse_sdo_result("-1 0"), Err(Error::SdoAbort(0xFFFF_FFFF)));
+        assert_eq!(parse_sdo_result("100794368 0"), Err(Error::SdoAbort(0x0602_0000)));
+        assert!(parse_sdo_result("").is_err());
+        assert!(parse_sdo_result("0 x").is_err());
+    }
+}
@@ -0,0 +1,338 @@
+use botnana::Botnana;
+use data_pool::{DataPool, Slave, Snapshot};
+use error::{Error, Result};
+use serde_json::{self, Value};
+use std::{fs, path::Path};
+
+/// EtherCAT 從站的拓樸資訊 (由 `.ec-links` 與 `.slave` 的回應取得)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlaveInfo {
+    /// Slave position，最靠近主站的為 1
+    pub position: u16,
+    /// Vendor ID
+    pub vendor_id: u32,
+    /// Product Code
+    pub product_code: u32,
+    /// Slave description
+    pub description: String,
+    /// Slave Alias，None 表示不比較
+    pub alias: Option<u16>,
+    /// 驅動器 channel 數，None 表示不知道或不比較
+    pub channels: Option<u16>,
+    /// AL state
+    pub al_state: u8,
+}
+
+impl SlaveInfo {
+    /// 接線是否相同 (不比較 description 與 AL state)
+    /// alias, channels 任一方為 None 時不比較
+    fn same_wiring(&self, other: &SlaveInfo) -> bool {
+        fn same<T: PartialEq>(a: Option<T>, b: Option<T>) -> bool {
+            match (a, b) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            }
+        }
+        self.position == other.position
+            && self.vendor_id == other.vendor_id
+            && self.product_code == other.product_code
+            && same(self.alias, other.alias)
+            && same(self.channels, other.channels)
+    }
+
+    fn from_slave(position: usize, slave: &Slave) -> SlaveInfo {
+        SlaveInfo {
+            position: position as u16,
+            vendor_id: slave.vendor_id,
+            product_code: slave.product_code,
+            description: slave.description.clone(),
+            alias: Some(slave.alias),
+            // 從站沒有回報 channel 數，data pool 中的 drives 只有收到過資料的 channel
+            channels: None,
+            al_state: slave.ec_state,
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        serde_json::json!({
+            "position": self.position,
+            "vendor_id": self.vendor_id,
+            "product_code": self.product_code,
+            "description": self.description,
+            "alias": self.alias,
+            "channels": self.channels,
+            "al_state": self.al_state,
+        })
+    }
+
+    fn from_value(value: &Value) -> Result<SlaveInfo> {
+        let field = |name: &str| -> Result<u64> {
+            match value.get(name) {
+                Some(Value::Number(x)) => x.as_u64(),
+                Some(Value::String(x)) => {
+                    if x.starts_with("0x") || x.starts_with("0X") {
+                        u64::from_str_radix(&x[2..], 16).ok()
+                    } else {
+                        x.parse::<u64>().ok()
+                    }
+                }
+                _ => None,
+            }
+            .ok_or_else(|| Error::Parse(format!("Invalid topology field {}: {}", name, value)))
+        };
+        let optional = |name: &str| -> Result<Option<u64>> {
+            match value.get(name) {
+                None | Some(Value::Null) => Ok(None),
+                Some(_) => field(name).map(Some),
+            }
+        };
+        Ok(SlaveInfo {
+            position: field("position")? as u16,
+            vendor_id: field("vendor_id")? as u32,
+            product_code: field("product_code")? as u32,
+            description: value
+                .get("description")
+                .and_then(Value::as_str)
+                .unwrap_or("")
+                .to_owned(),
+            alias: optional("alias")?.map(|x| x as u16),
+            channels: optional("channels")?.map(|x| x as u16),
+            al_state: optional("al_state")?.unwrap_or(0) as u8,
+        })
+    }
+}
+
+/// 與預期拓樸的差異
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TopologyDiff {
+    /// 預期的從站不存在
+    Missing(SlaveInfo),
+    /// 多出預期以外的從站
+    Unexpected(SlaveInfo),
+    /// 從站與預期不同
+    Mismatch {
+        expected: SlaveInfo,
+        actual: SlaveInfo,
+    },
+}
+
+/// EtherCAT 拓樸 (依 position 排列的從站)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Topology {
+    /// 從站資訊
+    pub slaves: Vec<SlaveInfo>,
+}
+
+impl Topology {
+    /// 由 data pool 建立 (需要先啟動自動取得資料的功能)
+    pub fn from_data_pool(data_pool: &DataPool) -> Topology {
+        Topology::from_slaves(&data_pool.slaves)
+    }
+
+    /// 由 data pool 的快照建立
+    pub fn from_snapshot(snapshot: &Snapshot) -> Topology {
+        Topology::from_slaves(&snapshot.slaves)
+    }
+
+    fn from_slaves(slaves: &[Slave]) -> Topology {
+        Topology {
+            slaves: slaves
+                .iter()
+                .enumerate()
+                .skip(1)
+                .map(|(position, slave)| SlaveInfo::from_slave(position, slave))
+                .collect(),
+        }
+    }
+
+    /// 由 JSON 字串建立，格式與 `to_json` 相同
+    /// 數字欄位可以用數字或字串，`0x` 開頭的字串為 hex (例如 `"0x0000066F"`)
+    /// alias, channels 沒有或為 null 時不比較
+    pub fn from_json(json: &str) -> Result<Topology> {
+        let value: Value = serde_json::from_str(json).map_err(|e| Error::Parse(e.to_string()))?;
+        let slaves = value
+            .get("slaves")
+            .and_then(Value::as_array)
+            .ok_or_else(|| Error::Parse(format!("Invalid topology: {}", json)))?;
+        Ok(Topology {
+            slaves: slaves
+                .iter()
+                .map(SlaveInfo::from_value)
+                .collect::<Result<Vec<_>>>()?,
+        })
+    }
+
+    /// 讀取預期的拓樸檔 (JSON)
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Topology> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .map_err(|e| Error::Parse(format!("{}: {}", path.display(), e)))?;
+        Topology::from_json(&json)
+    }
+
+    /// 轉成 JSON 字串，可以存成預期的拓樸檔
+    pub fn to_json(&self) -> String {
+        let slaves: Vec<Value> = self.slaves.iter().map(SlaveInfo::to_value).collect();
+        serde_json::json!({ "slaves": slaves }).to_string()
+    }
+
+    /// 與預期的拓樸比較，依 position 列出差異
+    /// 只比較接線相關的資訊 (vendor, product, alias, channels)，
+    /// 依 position 比對，從站順序改變時會列出 Mismatch
+    pub fn diff(&self, expected: &Topology) -> Vec<TopologyDiff> {
+        let mut diffs = Vec::new();
+        for expected in &expected.slaves {
+            match self.slaves.iter().find(|x| x.position == expected.position) {
+                Some(actual) if !actual.same_wiring(expected) => {
+                    diffs.push(TopologyDiff::Mismatch {
+                        expected: expected.clone(),
+                        actual: actual.clone(),
+                    });
+                }
+                Some(_) => {}
+                None => diffs.push(TopologyDiff::Missing(expected.clone())),
+            }
+        }
+        for actual in &self.slaves {
+            if !expected.slaves.iter().any(|x| x.position == actual.position) {
+                diffs.push(TopologyDiff::Unexpected(actual.clone()));
+            }
+        }
+        diffs
+    }
+}
+
+impl Botnana {
+    /// 目前的 EtherCAT 拓樸 (需要先啟動自動取得資料的功能)
+    pub fn topology(&self) -> Topology {
+        Topology::from_snapshot(&self.snapshot())
+    }
+
+    /// 檢查目前的 EtherCAT 拓樸與預期相同，不同時回傳 `Error::TopologyMismatch`
+    /// 可在啟動機台前確認接線沒有改變
+    pub fn check_topology(&self, expected: &Topology) -> Result<()> {
+        let diffs = self.topology().diff(expected);
+        if diffs.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::TopologyMismatch(diffs))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slave(position: u16, product_code: u32) -> SlaveInfo {
+        SlaveInfo {
+            position,
+            vendor_id: 0x66F,
+            product_code,
+            description: String::new(),
+            alias: None,
+            channels: None,
+            al_state: 0,
+        }
+    }
+
+    fn topology(slaves: Vec<SlaveInfo>) -> Topology {
+        Topology { slaves }
+    }
+
+    #[test]
+    fn from_json_numbers() {
+        let topology = Topology::from_json(
+            r#"{"slaves": [
+                {"position": 1, "vendor_id": "0x0000066F", "product_code": "0x10",
+                 "description": "D1", "alias": "16"},
+                {"position": 2, "vendor_id": 1647, "product_code": "16", "channels": null}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(topology.slaves.len(), 2);
+        assert_eq!(topology.slaves[0].vendor_id, 0x66F);
+        assert_eq!(topology.slaves[0].product_code, 0x10);
+        assert_eq!(topology.slaves[0].description, "D1");
+        // 沒有 0x 的字串為十進位
+        assert_eq!(topology.slaves[0].alias, Some(16));
+        assert_eq!(topology.slaves[0].channels, None);
+        assert_eq!(topology.slaves[1].vendor_id, 0x66F);
+        assert_eq!(topology.slaves[1].product_code, 16);
+        assert_eq!(topology.slaves[1].alias, None);
+        assert!(Topology::from_json(r#"{"slaves": [{"position": "1A"}]}"#).is_err());
+        assert!(Topology::from_json(r#"{"slaves": [{"position": 1}]}"#).is_err());
+        assert!(Topology::from_json("{}").is_err());
+    }
+
+    #[test]
+    fn json_round_trip() {
+        let mut first = slave(1, 0x10);
+        first.alias = Some(3);
+        let expected = topology(vec![first, slave(2, 0x20)]);
+        assert_eq!(Topology::from_json(&expected.to_json()), Ok(expected));
+    }
+
+    #[test]
+    fn diff_match() {
+        let expected = topology(vec![slave(1, 0x10), slave(2, 0x20)]);
+        let mut actual = expected.clone();
+        // 預期的拓樸沒有 alias, channels 時不比較
+        actual.slaves[0].alias = Some(7);
+        actual.slaves[1].description = "other".to_owned();
+        assert_eq!(actual.diff(&expected), vec![]);
+    }
+
+    #[test]
+    fn diff_optional_fields() {
+        let mut expected = topology(vec![slave(1, 0x10)]);
+        expected.slaves[0].alias = Some(1);
+        let mut actual = expected.clone();
+        actual.slaves[0].alias = Some(2);
+        assert_eq!(
+            actual.diff(&expected),
+            vec![TopologyDiff::Mismatch {
+                expected: expected.slaves[0].clone(),
+                actual: actual.slaves[0].clone(),
+            }]
+        );
+        // 實際的 channel 數不知道時不比較
+        expected.slaves[0].alias = None;
+        expected.slaves[0].channels = Some(2);
+        assert_eq!(actual.diff(&expected), vec![]);
+    }
+
+    #[test]
+    fn diff_missing_and_extra() {
+        let expected = topology(vec![slave(1, 0x10), slave(2, 0x20)]);
+        let actual = topology(vec![slave(1, 0x10)]);
+        assert_eq!(
+            actual.diff(&expected),
+            vec![TopologyDiff::Missing(slave(2, 0x20))]
+        );
+        let actual = topology(vec![slave(1, 0x10), slave(2, 0x20), slave(3, 0x30)]);
+        assert_eq!(
+            actual.diff(&expected),
+            vec![TopologyDiff::Unexpected(slave(3, 0x30))]
+        );
+    }
+
+    #[test]
+    fn diff_reordered() {
+        let expected = topology(vec![slave(1, 0x10), slave(2, 0x20)]);
+        let actual = topology(vec![slave(1, 0x20), slave(2, 0x10)]);
+        assert_eq!(
+            actual.diff(&expected),
+            vec![
+                TopologyDiff::Mismatch {
+                    expected: slave(1, 0x10),
+                    actual: slave(1, 0x20),
+                },
+                TopologyDiff::Mismatch {
+                    expected: slave(2, 0x20),
+                    actual: slave(2, 0x10),
+                },
+            ]
+        );
+    }
+}